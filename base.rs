@@ -1,21 +1,44 @@
 use std::collections::HashMap;
 
 use macroquad::prelude as mq;
+use serde::Deserialize;
 
 use crate::prelude::*;
 
+/// What a base's guards do once the player picks it up
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum GuardReaction {
+    #[default]
+    Chase,    // Go after whoever is carrying it, however far away, until it's dropped
+    Scatter,  // Flee from where it was for a few seconds, then roam
+    FallBack, // Guard the nearest base of the same side that's still there, or roam if there isn't one
+}
+
+/// A `GuardReaction` worked out for one particular capture, sent to each guard
+#[derive(Debug, Clone, Copy)]
+pub enum GuardOrders {
+    Chase { carrier: EntityId },
+    Scatter { from: mq::Vec2, until: f32 }, // `until` is in elapsed game time
+    FallBack { base: Option<EntityId> },
+}
+
 #[derive(Clone)]
 pub struct Base {
+    pub id: EntityId,
     pub game_object: GameObject,
     pub enemy_counts: HashMap<EnemyShipType, u32>,
     pub optimal_distance: f32,
     pub max_distance: f32,
     pub collected: bool,
+    pub origin: mq::Vec2, // Where the base returns to if the player loses it
+    pub team: Team, // The side its guards fight for
+    pub guards: Vec<EntityId>, // The ships spawned around it, including ones since destroyed
+    pub guard_reaction: GuardReaction,
 }
 
 impl Default for Base {
     fn default() -> Self {
-        Base { game_object: GameObject::default(), enemy_counts: HashMap::new(), optimal_distance: 100.0, max_distance: 1000.0, collected: false }
+        Base { id: EntityId::default(), game_object: GameObject::default(), enemy_counts: HashMap::new(), optimal_distance: 100.0, max_distance: 1000.0, collected: false, origin: mq::Vec2::ZERO, team: Team::Hostile, guards: vec![], guard_reaction: GuardReaction::default() }
     }
 }
 
@@ -27,19 +50,35 @@ impl Base {
             draw_shapes: vec![DrawShape::new_circle_color(mq::RED)],
             ..Default::default()
         };
-        Base { game_object, enemy_counts, optimal_distance, max_distance, collected: false }
+        Base { id: EntityId::default(), game_object, enemy_counts, optimal_distance, max_distance, collected: false, origin: position, team: Team::Hostile, guards: vec![], guard_reaction: GuardReaction::default() }
+    }
+    /// Hands the base and its guards to another side, drawn in that side's color
+    pub fn with_team(mut self, team: Team) -> Self {
+        self.team = team;
+        if team != Team::Hostile {
+            self.game_object.draw_shapes = vec![DrawShape::new_circle_color(team.get_color())];
+        }
+        self
+    }
+    pub fn with_guard_reaction(mut self, guard_reaction: GuardReaction) -> Self {
+        self.guard_reaction = guard_reaction;
+        self
     }
-    pub fn get_enemies(&self) -> Vec<Ship> {
+    pub fn get_enemies(&self, rng: &mut SeededRng, archetypes: &EnemyArchetypes) -> Vec<Ship> {
         let mut enemies = vec![];
-        for (enemy_type, count) in self.enemy_counts.clone() {
+        // Sorted so the spawn order, and therefore the RNG draws, don't depend on HashMap ordering
+        let mut enemy_counts: Vec<_> = self.enemy_counts.clone().into_iter().collect();
+        enemy_counts.sort();
+        for (enemy_type, count) in enemy_counts {
+            let archetype = archetypes.get(&enemy_type).expect("level files are checked against the archetypes when loaded");
             for i in 0..count {
                 let offset = 2.0 * ((i as i32 - count as i32 / 2) as f32) / (count as f32);  // From -1 to 1
                 let offset = offset * PI / 4.0;  // From -45 to 45 degrees
-                enemies.push(Ship::new_enemy(enemy_type, Some(self.clone()), offset));
+                enemies.push(Ship::new_enemy(archetype, Some(self.id), offset, self.team));
             }
         }
         for enemy in &mut enemies {
-            let direction = mq::rand::gen_range(-PI, PI);
+            let direction = rng.gen_range(-PI, PI);
             enemy.game_object.position = self.game_object.position + self.optimal_distance * mq::Vec2::from_angle(direction);
             enemy.game_object.direction = Direction::new(direction);
         }
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+use crate::level::level_file::{LevelFile, LevelFileError};
+
+#[derive(Debug, Clone)]
+pub enum LevelSource {
+    BuiltIn, // `levels/level_1.ron` as compiled in, for replays that don't name a level file
+    File(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct CampaignLevel {
+    pub name: String,
+    pub source: LevelSource,
+    pub bases_to_unlock_next: u32, // Bases that must be brought home from this level to unlock the next one
+}
+
+/// The levels in play order.
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+/// What the player has achieved so far across the whole campaign.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CampaignProgress {
+    pub results: HashMap<String, LevelResult>, // By level name
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelResult {
+    pub best_bases_collected: u32,
+    pub best_time: Option<f32>, // Fastest return home with `best_bases_collected` bases, in seconds
+    pub deaths: u32,
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        Campaign {
+            levels: vec![
+                CampaignLevel { name: "Level 1".to_string(), source: LevelSource::File("levels/level_1.ron".to_string()), bases_to_unlock_next: 5 },
+                CampaignLevel { name: "Level 2".to_string(), source: LevelSource::File("levels/level_2.ron".to_string()), bases_to_unlock_next: 5 },
+                CampaignLevel { name: "Level 3".to_string(), source: LevelSource::File("levels/level_3.ron".to_string()), bases_to_unlock_next: 5 },
+                CampaignLevel { name: "Level 4".to_string(), source: LevelSource::File("levels/level_4.ron".to_string()), bases_to_unlock_next: 0 },
+            ],
+        }
+    }
+}
+
+impl Campaign {
+    /// The first level is always unlocked, every other one once enough bases are brought home from the level before
+    pub fn is_unlocked(&self, index: usize, progress: &CampaignProgress) -> bool {
+        if index == 0 {
+            return true;
+        }
+        let previous = &self.levels[index - 1];
+        self.is_unlocked(index - 1, progress) && progress.get_best(&previous.name) >= previous.bases_to_unlock_next
+    }
+}
+
+impl CampaignLevel {
+    pub fn load(&self, archetypes: &EnemyArchetypes) -> Result<Box<dyn Level>, LevelFileError> {
+        match &self.source {
+            LevelSource::BuiltIn => Ok(Box::new(LevelFile::built_in(archetypes)?)),
+            LevelSource::File(path) => Ok(Box::new(LevelFile::load(path, archetypes)?)),
+        }
+    }
+
+    /// The path replays store to find this level again
+    pub fn get_path(&self) -> Option<String> {
+        match &self.source {
+            LevelSource::BuiltIn => None,
+            LevelSource::File(path) => Some(path.clone()),
+        }
+    }
+}
+
+impl CampaignProgress {
+    pub fn get_best(&self, level_name: &str) -> u32 {
+        self.results.get(level_name).map_or(0, |result| result.best_bases_collected)
+    }
+
+    /// Bases the player has to spend in the shop, in total: the best base counts across all levels added up,
+    /// so replaying a level only pays out for bases beyond its previous best
+    pub fn get_bases_earned(&self) -> u32 {
+        self.results.values().map(|result| result.best_bases_collected).sum()
+    }
+
+    /// Records bases brought home after `time` seconds; returns whether this beat the previous best for the level
+    pub fn bank_bases(&mut self, level_name: &str, bases_collected: u32, time: f32) -> bool {
+        let result = self.results.entry(level_name.to_string()).or_default();
+        let is_faster = result.best_time.is_none_or(|best_time| time < best_time);
+        if bases_collected > result.best_bases_collected || (bases_collected == result.best_bases_collected && is_faster) {
+            result.best_bases_collected = bases_collected;
+            result.best_time = Some(time);
+            return true;
+        }
+        false
+    }
+
+    pub fn record_deaths(&mut self, level_name: &str, deaths: u32) {
+        self.results.entry(level_name.to_string()).or_default().deaths += deaths;
+    }
+}
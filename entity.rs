@@ -0,0 +1,32 @@
+/// Names a ship, bullet or base for as long as it exists, however the lists holding them get filtered.
+/// Ids are never reused within a game, so a handle to something that's gone just stops resolving.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(u64);
+
+impl EntityId {
+    /// Whether it's this entity's turn on tick `tick` for work done once every `period` ticks.
+    /// Different ids get different turns, so the work is spread out rather than all landing on one tick.
+    pub fn is_due(self, tick: u64, period: u64) -> bool {
+        (self.0 + tick).is_multiple_of(period)
+    }
+}
+
+/// Hands out ids in increasing order. The default id, 0, is never handed out, and marks things not yet added to a game.
+#[derive(Debug, Clone)]
+pub struct EntityIds {
+    next: u64,
+}
+
+impl Default for EntityIds {
+    fn default() -> Self {
+        EntityIds { next: 1 }
+    }
+}
+
+impl EntityIds {
+    pub fn allocate(&mut self) -> EntityId {
+        let id = EntityId(self.next);
+        self.next += 1;
+        id
+    }
+}
@@ -0,0 +1,68 @@
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+const EXPLOSION_DRAW_TIME: f32 = 0.3;
+
+/// How big an explosion is and how hard it hits. Damage and knockback are at full strength
+/// at the centre and fall off linearly to nothing at `radius`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplosionStats {
+    pub radius: f32,
+    pub damage: f32,
+    #[serde(default)]
+    pub knockback: f32, // Impulse, so heavier (bigger) ships are pushed less
+}
+
+#[derive(Debug, Clone)]
+pub struct Explosion {
+    pub position: mq::Vec2,
+    pub stats: ExplosionStats,
+    pub team: Option<Team>,  // `None` hurts everyone, like an exploding ship
+    pub hurts_own_team: bool,
+    pub time_remaining: f32, // For drawing; the damage is all dealt on the tick it goes off
+}
+
+impl Explosion {
+    pub fn new(position: mq::Vec2, stats: ExplosionStats, team: Option<Team>, hurts_own_team: bool) -> Self {
+        Explosion { position, stats, team, hurts_own_team, time_remaining: EXPLOSION_DRAW_TIME }
+    }
+
+    pub fn can_damage(&self, rules: &TeamRules, target_team: Team) -> bool {
+        match self.team {
+            Some(team) => rules.is_hostile(team, target_team) || self.hurts_own_team,
+            None => true,
+        }
+    }
+
+    /// Damages and pushes `target` by how close its edge is to the centre.
+    /// Returns whether this killed it, so its own death explosion can go off in turn.
+    pub fn hit(&self, target: &mut GameObject) -> bool {
+        let offset = target.position - self.position;
+        let distance = (offset.length() - target.radius).max(0.0);
+        if distance >= self.stats.radius || target.health_status == HealthStatus::Dead {
+            return false;
+        }
+        let strength = 1.0 - distance / self.stats.radius;
+        let mass = target.radius * target.radius;
+        target.velocity += offset.normalize_or_zero() * self.stats.knockback * strength / mass;
+        target.apply_damage(self.stats.damage * strength);
+        target.health_status == HealthStatus::Dead
+    }
+
+    pub fn update(&mut self, delta_t: f32) {
+        self.time_remaining -= delta_t;
+    }
+
+    /// A ring that grows out to the blast radius and fades
+    pub fn draw(&self, camera: &Camera) {
+        let progress = 1.0 - self.time_remaining / EXPLOSION_DRAW_TIME;
+        let relative_pos = (self.position - camera.position) * camera.zoom;
+        let mut color = mq::ORANGE;
+        color.a = 1.0 - progress;
+        let radius = self.stats.radius * progress.sqrt() * camera.zoom;
+        mq::draw_circle_lines(relative_pos.x, relative_pos.y, radius, f32::max(1.0, 3.0 * camera.zoom), color);
+    }
+}
@@ -2,7 +2,28 @@ use macroquad::prelude as mq;
 
 use crate::prelude::*;
 
-const UPDATE_ENEMY_RADIUS: f32 = 3000.0;
+use crate::spatial_grid::SpatialGrid;
+use crate::weapon::bullet::BulletKind;
+use crate::explosion::Explosion;
+use crate::world::{BaseSnapshot, BulletSnapshot, ShipSnapshot};
+use crate::entity::EntityIds;
+use crate::base::{GuardOrders, GuardReaction};
+
+// Enemies are simulated in less detail the further they are from the player. Near the player they get
+// full AI, collisions and bullet hits every tick. Further out they still think and fly, but only every
+// few ticks in one bigger step, without firing, and nothing hits them. Beyond that they only drift, once a second.
+const FULL_SIMULATION_RADIUS: f32 = 3000.0;
+const COARSE_SIMULATION_RADIUS: f32 = 8000.0;
+const COARSE_SIMULATION_TICKS: u64 = 8;
+const DORMANT_SIMULATION_TICKS: u64 = 120;
+const COLLISION_CELL_SIZE: f32 = 100.0;
+const TARGET_CELL_SIZE: f32 = 500.0; // Coarser, since ships look for targets much further away than they collide
+const STARTING_LIVES: u32 = 3;
+const RESPAWN_INVULNERABLE_TIME: f32 = 3.0;
+const INVULNERABLE_BLINK_RATE: f32 = 8.0; // Blinks per second
+const SCATTER_TIME: f32 = 4.0; // How long guards told to scatter flee before roaming
+pub const TICK_RATE: f32 = 120.0;
+pub const TICK_DURATION: f32 = 1.0 / TICK_RATE;
 
 #[derive(Clone)]
 pub struct Game {
@@ -10,95 +31,424 @@ pub struct Game {
     pub camera: Camera,
     pub home_base: GameObject,
     pub bases: Vec<Base>,
-    pub enemies: Vec<Ship>,
+    pub enemies: Vec<Ship>, // Only ever added to in id order, so they stay sorted by id for lookups
     pub bullets: Vec<Bullet>,
+    pub explosions: Vec<Explosion>,
+    pub world: WorldSnapshot, // What controllers see this tick
+    pub enemies_fight_each_other: bool,
+    pub enemy_collisions: bool,
+    pub team_rules: TeamRules,
     pub gameover: bool,
     pub total_enemy_count: usize,
     pub player_speed_multiplier: f32,
     pub enemy_speed_multiplier: f32,
     pub collected_base_count: u32,
+    pub input: PlayerInput,
+    pub seed: u64,
+    pub rng: SeededRng,
+    pub interpolation: f32,
+    pub elapsed_time: f32,
+    pub ticks: u64,
+    pub lives: u32,
+    pub deaths: u32,
+    pub player_invulnerable_time: f32,
+    pub upgrades: Upgrades,
+    pub bases_to_spend: u32,
+    pub entity_ids: EntityIds,
 }
 
 impl Game {
-    pub fn new(level: impl Level, player_level: f32) -> Option<Self> {
+    pub fn new(level: &dyn Level, archetypes: &EnemyArchetypes, upgrades: &Upgrades, bases_to_spend: u32, seed: u64) -> Option<Self> {
         let home_base = GameObject {
             radius: 100.0,
             draw_shapes: vec![DrawShape::new_circle_color(mq::BLUE)],
             ..Default::default()
         };
-        let bases = level.get_bases();
+        let mut entity_ids = EntityIds::default();
+        let mut player = Ship::new_player(upgrades);
+        player.id = entity_ids.allocate();
+        let mut bases = level.get_bases();
+        for base in &mut bases {
+            base.id = entity_ids.allocate();
+        }
         let spawn_regions = level.get_spawn_regions();
+        let mut rng = SeededRng::new(seed);
         let mut enemies = vec![];
-        for base in &bases {
-            enemies.extend(base.get_enemies());
+        for base in &mut bases {
+            let mut guards = base.get_enemies(&mut rng, archetypes);
+            for guard in &mut guards {
+                guard.id = entity_ids.allocate();
+            }
+            base.guards = guards.iter().map(|guard| guard.id).collect();
+            enemies.extend(guards);
         }
         for spawn_region in &spawn_regions {
-            enemies.extend(spawn_region.get_enemies());
+            let mut spawned = spawn_region.get_enemies(&mut rng, archetypes);
+            for enemy in &mut spawned {
+                enemy.id = entity_ids.allocate();
+            }
+            enemies.extend(spawned);
         }
         let total_enemy_count = enemies.len();
-        Some(Game {
-            player: Ship::new_player(player_level),
+        let team_rules = level.get_team_rules();
+        let mut enemy_teams: Vec<Team> = vec![];
+        for enemy in &enemies {
+            if !enemy_teams.contains(&enemy.team) {
+                enemy_teams.push(enemy.team);
+            }
+        }
+        let enemies_fight_each_other = enemy_teams.iter().any(|&x| enemy_teams.iter().any(|&y| team_rules.is_hostile(x, y)));
+        let world = WorldSnapshot {
+            input: PlayerInput::default(),
+            elapsed_time: 0.0,
+            player: ShipSnapshot::from(&player),
+            enemies: vec![],
+            bullets: vec![],
+            bases: vec![],
+            enemy_grid: SpatialGrid::from_circles(COLLISION_CELL_SIZE, std::iter::empty()),
+            target_grid: SpatialGrid::from_circles(TARGET_CELL_SIZE, std::iter::empty()),
+            enemies_fight_each_other,
+            team_rules: team_rules.clone(),
+        };
+        let mut game = Game {
+            player,
             camera: Camera::default(),
             home_base,
             bases,
             enemies,
             bullets: vec![],
+            explosions: vec![],
+            world,
+            enemies_fight_each_other,
+            enemy_collisions: level.has_enemy_collisions(),
+            team_rules,
             gameover: false,
             total_enemy_count,
             player_speed_multiplier: 1.0,
             enemy_speed_multiplier: 1.0,
             collected_base_count: 0,
-        })
+            input: PlayerInput::default(),
+            seed,
+            rng,
+            interpolation: 1.0,
+            elapsed_time: 0.0,
+            ticks: 0,
+            lives: STARTING_LIVES,
+            deaths: 0,
+            player_invulnerable_time: 0.0,
+            upgrades: upgrades.clone(),
+            bases_to_spend,
+            entity_ids,
+        };
+        game.store_previous_positions();
+        Some(game)
+    }
+
+    fn store_previous_positions(&mut self) {
+        self.player.game_object.previous_position = self.player.game_object.position;
+        for enemy in &mut self.enemies {
+            enemy.game_object.previous_position = enemy.game_object.position;
+        }
+        for bullet in &mut self.bullets {
+            bullet.game_object.previous_position = bullet.game_object.position;
+        }
+        for base in &mut self.bases {
+            base.game_object.previous_position = base.game_object.position;
+        }
+        self.home_base.previous_position = self.home_base.position;
     }
 
-    pub fn update(&mut self, delta_t: f32) {
-        let clone = self.clone();
+    pub fn update(&mut self, delta_t: f32, input: PlayerInput) {
+        // Holding a buy key only buys once
+        if input.buy_upgrade != self.input.buy_upgrade {
+            if let Some(kind) = input.buy_upgrade {
+                self.buy_upgrade(kind);
+            }
+        }
+        self.input = input;
+        self.elapsed_time += delta_t;
+        self.ticks += 1;
+        // Beams only last the tick they were fired in, and were only kept this long to be drawn
+        self.bullets.retain(|bullet| bullet.kind != BulletKind::Beam);
+        for explosion in &mut self.explosions {
+            explosion.update(delta_t);
+        }
+        self.explosions.retain(|explosion| explosion.time_remaining > 0.0);
+        self.store_previous_positions();
+        self.world = self.take_snapshot();
         let mut bullets_to_add = vec![];
-        self.player.update(delta_t * self.player_speed_multiplier, &clone, &mut bullets_to_add);
+        self.player.update(delta_t * self.player_speed_multiplier, &self.world, &mut bullets_to_add);
         for enemy in &mut self.enemies {
-            if mq::Vec2::distance(self.player.game_object.position, enemy.game_object.position) > UPDATE_ENEMY_RADIUS {
-                continue;
+            let distance = mq::Vec2::distance(self.player.game_object.position, enemy.game_object.position);
+            let delta_t = delta_t * self.enemy_speed_multiplier;
+            if distance <= FULL_SIMULATION_RADIUS {
+                enemy.update(delta_t, &self.world, &mut bullets_to_add);
+            } else if distance <= COARSE_SIMULATION_RADIUS {
+                if enemy.id.is_due(self.ticks, COARSE_SIMULATION_TICKS) {
+                    enemy.update_movement(delta_t * COARSE_SIMULATION_TICKS as f32, &self.world);
+                }
+            } else if enemy.id.is_due(self.ticks, DORMANT_SIMULATION_TICKS) {
+                enemy.game_object.update(delta_t * DORMANT_SIMULATION_TICKS as f32);
+            }
+        }
+        let mut enemy_grid = self.get_nearby_enemy_grid();
+        if self.enemy_collisions && self.bounce_enemies(&enemy_grid) {
+            enemy_grid = self.get_nearby_enemy_grid(); // Bouncing moved them
+        }
+        let (player_position, player_radius) = self.player.game_object.get_swept_bounds();
+        for index in enemy_grid.query(player_position, player_radius) {
+            let enemy = &mut self.enemies[index];
+            if self.team_rules.is_hostile(self.player.team, enemy.team) {
+                GameObject::ram_if_overlapping(&mut self.player.game_object, &mut enemy.game_object);
+            } else {
+                GameObject::bounce_if_overlapping(&mut self.player.game_object, &mut enemy.game_object);
             }
-            enemy.update(delta_t * self.enemy_speed_multiplier, &clone, &mut bullets_to_add);
-            GameObject::kill_if_overlapping(&mut self.player.game_object, &mut enemy.game_object);
         }
-        self.bullets.extend(bullets_to_add);
+        for mut bullet in bullets_to_add {
+            bullet.id = self.entity_ids.allocate();
+            self.bullets.push(bullet);
+        }
         for bullet in &mut self.bullets {
-            if mq::Vec2::distance(self.player.game_object.position, bullet.game_object.position) > UPDATE_ENEMY_RADIUS {
+            let speed_multiplier = if bullet.team == Team::Player { self.player_speed_multiplier } else { self.enemy_speed_multiplier };
+            if mq::Vec2::distance(self.player.game_object.position, bullet.game_object.position) > FULL_SIMULATION_RADIUS {
+                bullet.coast(delta_t * speed_multiplier); // Still runs out, but can't steer or hit anything
                 continue;
             }
-            match bullet.team {
-                Team::Player => {
-                    bullet.update(delta_t * self.player_speed_multiplier);
-                    for enemy in &mut self.enemies {
-                        GameObject::kill_if_overlapping(&mut bullet.game_object, &mut enemy.game_object);
-                    }
-                }
-                Team::Hostile => {
-                    bullet.update(delta_t * self.enemy_speed_multiplier);
-                    GameObject::kill_if_overlapping(&mut bullet.game_object, &mut self.player.game_object);
+            bullet.update(delta_t * speed_multiplier, &self.world);
+            if self.team_rules.can_damage(bullet.team, bullet.age, self.player.team) {
+                bullet.hit_if_overlapping(&mut self.player.game_object);
+            }
+            let (bullet_position, bullet_radius) = bullet.game_object.get_swept_bounds();
+            for index in enemy_grid.query(bullet_position, bullet_radius) {
+                let enemy = &mut self.enemies[index];
+                if self.team_rules.can_damage(bullet.team, bullet.age, enemy.team) {
+                    bullet.hit_if_overlapping(&mut enemy.game_object);
                 }
             }
         }
-        for base in &mut self.bases {
+        self.shoot_down_missiles();
+        self.set_off_explosions(&enemy_grid);
+        let base_grid = SpatialGrid::from_game_objects(COLLISION_CELL_SIZE, self.bases.iter().map(|base| &base.game_object));
+        for index in base_grid.query(self.player.game_object.position, self.player.game_object.radius) {
+            let base = &mut self.bases[index];
             if !base.collected && GameObject::is_overlapping(&self.player.game_object, &base.game_object) {
                 base.collected = true;
                 self.collected_base_count += 1;
+                self.alert_guards(index);
             }
+        }
+        for base in &mut self.bases {
             if base.collected {
                 base.game_object.position = self.player.game_object.position;
             }
         }
+        self.player_invulnerable_time -= delta_t;
+        if self.player_invulnerable_time <= 0.0 && self.player.game_object.health_status == HealthStatus::Invulnerable {
+            self.player.game_object.health_status = HealthStatus::Alive;
+        }
         if self.player.game_object.health_status == HealthStatus::Dead {
-            self.gameover = true;
+            self.deaths += 1;
+            self.lives = self.lives.saturating_sub(1);
+            if self.lives == 0 {
+                self.gameover = true;
+            } else {
+                self.respawn_player();
+            }
         }
         self.enemies.retain(|enemy| enemy.game_object.health_status != HealthStatus::Dead);
         self.bullets.retain(|bullets| bullets.game_object.health_status != HealthStatus::Dead);
         self.bullets.retain(|bullet| bullet.lifetime_remaining > 0.0);
     }
 
+    /// Returns whether any enemies touched
+    fn bounce_enemies(&mut self, enemy_grid: &SpatialGrid) -> bool {
+        let mut has_bounced = false;
+        let player_position = self.player.game_object.position;
+        for index in 0..self.enemies.len() {
+            if self.enemies[index].game_object.position.distance(player_position) > FULL_SIMULATION_RADIUS {
+                continue;
+            }
+            let (position, radius) = self.enemies[index].game_object.get_swept_bounds();
+            for other_index in enemy_grid.query(position, radius) {
+                if other_index <= index {
+                    continue; // Each pair only once
+                }
+                let [enemy, other] = self.enemies.get_disjoint_mut([index, other_index]).expect("indices are distinct and in range");
+                if GameObject::is_overlapping(&enemy.game_object, &other.game_object) {
+                    GameObject::bounce_if_overlapping(&mut enemy.game_object, &mut other.game_object);
+                    has_bounced = true;
+                }
+            }
+        }
+        has_bounced
+    }
+
+    /// Lets bullets hit missiles they're allowed to damage
+    fn shoot_down_missiles(&mut self) {
+        let player_position = self.player.game_object.position;
+        let is_far = |bullet: &Bullet| bullet.game_object.position.distance(player_position) > FULL_SIMULATION_RADIUS;
+        let missiles = self.bullets.iter().enumerate().filter(|(_, bullet)| bullet.kind == BulletKind::Missile && !is_far(bullet));
+        let missile_grid = SpatialGrid::from_circles(COLLISION_CELL_SIZE, missiles.map(|(index, missile)| {
+            let (position, radius) = missile.game_object.get_swept_bounds();
+            (index, position, radius)
+        }));
+        for index in 0..self.bullets.len() {
+            if is_far(&self.bullets[index]) {
+                continue; // Coasting, like in update
+            }
+            let (position, radius) = self.bullets[index].game_object.get_swept_bounds();
+            for missile_index in missile_grid.query(position, radius) {
+                let (bullet, missile) = (&self.bullets[index], &self.bullets[missile_index]);
+                if missile_index == index || !self.team_rules.can_damage(bullet.team, bullet.age, missile.team) {
+                    continue;
+                }
+                let [bullet, missile] = self.bullets.get_disjoint_mut([index, missile_index]).expect("indices are distinct and in range");
+                bullet.hit_if_overlapping(&mut missile.game_object);
+            }
+        }
+    }
+
+    /// Explosive bullets that hit something, were shot down or ran out go off, as do explosive enemies that died this tick.
+    /// Anything an explosion kills can explode in turn. Bullets past the full simulation radius just fizzle out, as they can't hit anything there either.
+    fn set_off_explosions(&mut self, enemy_grid: &SpatialGrid) {
+        let player_position = self.player.game_object.position;
+        let mut pending = vec![];
+        for bullet in &self.bullets {
+            let Some(stats) = bullet.explosion else {
+                continue;
+            };
+            if bullet.game_object.position.distance(player_position) > FULL_SIMULATION_RADIUS {
+                continue;
+            }
+            if bullet.game_object.health_status == HealthStatus::Dead || bullet.lifetime_remaining <= 0.0 {
+                let hurts_own_team = self.team_rules.allows_friendly_fire(bullet.age);
+                pending.push(Explosion::new(bullet.game_object.position, stats, Some(bullet.team), hurts_own_team));
+            }
+        }
+        for enemy in &self.enemies {
+            if let (HealthStatus::Dead, Some(stats)) = (enemy.game_object.health_status, enemy.death_explosion) {
+                pending.push(Explosion::new(enemy.game_object.position, stats, None, false));
+            }
+        }
+        while let Some(explosion) = pending.pop() {
+            if explosion.can_damage(&self.team_rules, self.player.team) {
+                explosion.hit(&mut self.player.game_object);
+            }
+            for index in enemy_grid.query(explosion.position, explosion.stats.radius) {
+                let enemy = &mut self.enemies[index];
+                if !explosion.can_damage(&self.team_rules, enemy.team) || !explosion.hit(&mut enemy.game_object) {
+                    continue;
+                }
+                if let Some(stats) = enemy.death_explosion {
+                    pending.push(Explosion::new(enemy.game_object.position, stats, None, false));
+                }
+            }
+            self.explosions.push(explosion);
+        }
+    }
+
+    /// Tells the guards of a base the player just picked up what to do about it
+    fn alert_guards(&mut self, base_index: usize) {
+        let base = &self.bases[base_index];
+        let orders = match base.guard_reaction {
+            GuardReaction::Chase => GuardOrders::Chase { carrier: self.player.id },
+            GuardReaction::Scatter => GuardOrders::Scatter { from: base.game_object.position, until: self.elapsed_time + SCATTER_TIME },
+            GuardReaction::FallBack => {
+                let position = base.game_object.position;
+                let fallback = self.bases.iter()
+                    .filter(|other| !other.collected && other.team == base.team)
+                    .min_by(|x, y| x.game_object.position.distance(position).total_cmp(&y.game_object.position.distance(position)));
+                GuardOrders::FallBack { base: fallback.map(|fallback| fallback.id) }
+            }
+        };
+        self.notify_guards(base_index, |controller| controller.on_base_collected(orders));
+    }
+
+    /// Calls `notify` with the controller of each of the base's guards that's still around
+    fn notify_guards(&mut self, base_index: usize, notify: impl Fn(&mut dyn ShipController)) {
+        for id in &self.bases[base_index].guards {
+            if let Ok(index) = self.enemies.binary_search_by_key(id, |enemy| enemy.id) {
+                notify(self.enemies[index].ship_controller.as_mut());
+            }
+        }
+    }
+
+    pub fn is_player_at_home(&self) -> bool {
+        GameObject::is_overlapping(&self.player.game_object, &self.home_base)
+    }
+
+    /// Only works at the home base, and takes effect straight away
+    fn buy_upgrade(&mut self, kind: UpgradeKind) {
+        if !self.is_player_at_home() || self.player.game_object.health_status == HealthStatus::Dead {
+            return;
+        }
+        if self.upgrades.buy(kind, &mut self.bases_to_spend) {
+            self.player.apply_upgrades(&self.upgrades);
+        }
+    }
+
+    /// Puts the player back at the home base, briefly invulnerable, and sends half the carried bases (rounded up) back where they came from
+    fn respawn_player(&mut self) {
+        let bases_to_lose = self.collected_base_count.div_ceil(2);
+        let lost: Vec<usize> = (0..self.bases.len()).filter(|&index| self.bases[index].collected).take(bases_to_lose as usize).collect();
+        for index in lost {
+            let base = &mut self.bases[index];
+            base.collected = false;
+            base.game_object.position = base.origin;
+            base.game_object.previous_position = base.origin;
+            self.notify_guards(index, |controller| controller.on_base_returned());
+        }
+        self.collected_base_count -= bases_to_lose;
+
+        let player = &mut self.player.game_object;
+        player.position = self.home_base.position;
+        player.previous_position = self.home_base.position;
+        player.velocity = mq::Vec2::ZERO;
+        player.health.hit_points = player.health.max_hit_points;
+        player.health.shield = player.health.max_shield;
+        player.health_status = HealthStatus::Invulnerable;
+        self.player_invulnerable_time = RESPAWN_INVULNERABLE_TIME;
+    }
+
+    /// Copies what controllers need from the start of the tick, so everything can then be updated in place
+    fn take_snapshot(&self) -> WorldSnapshot {
+        let target_grid = if self.enemies_fight_each_other {
+            let circles = self.enemies.iter().enumerate().map(|(index, enemy)| (index, enemy.game_object.position, 0.0));
+            SpatialGrid::from_circles(TARGET_CELL_SIZE, circles)
+        } else {
+            SpatialGrid::from_circles(TARGET_CELL_SIZE, std::iter::empty())
+        };
+        WorldSnapshot {
+            input: self.input,
+            elapsed_time: self.elapsed_time,
+            player: ShipSnapshot::from(&self.player),
+            enemies: self.enemies.iter().map(ShipSnapshot::from).collect(),
+            bullets: self.bullets.iter().map(BulletSnapshot::from).collect(),
+            bases: self.bases.iter().map(BaseSnapshot::from).collect(),
+            enemy_grid: self.get_nearby_enemy_grid(),
+            target_grid,
+            enemies_fight_each_other: self.enemies_fight_each_other,
+            team_rules: self.team_rules.clone(),
+        }
+    }
+
+    /// Only enemies in range of an updated bullet or the player can be hit this tick, so the rest are left out of the grid
+    fn get_nearby_enemy_grid(&self) -> SpatialGrid {
+        let player_position = self.player.game_object.position;
+        let max_distance = FULL_SIMULATION_RADIUS + COLLISION_CELL_SIZE;
+        let circles = self.enemies.iter()
+            .enumerate()
+            .filter(|(_, enemy)| enemy.game_object.position.distance(player_position) < max_distance)
+            .map(|(index, enemy)| {
+                let (position, radius) = enemy.game_object.get_swept_bounds();
+                (index, position, radius)
+            });
+        SpatialGrid::from_circles(COLLISION_CELL_SIZE, circles)
+    }
+
     pub fn draw(&mut self) {
-        self.camera.position = self.player.game_object.position - self.player.game_object.velocity * 0.0;
+        let player_position = self.player.game_object.get_draw_position(self.interpolation);
+        self.camera.position = player_position - self.player.game_object.velocity * 0.0;
         self.camera.position -= mq::Vec2::new(mq::screen_width() / 2.0, mq::screen_height() / 2.0) / self.camera.zoom;
 
         // Draw grid
@@ -109,8 +459,8 @@ impl Game {
         if line_half_count >= 192 {
             line_half_count = 0; // Don't render any lines
         }
-        let grid_mid_x = f32::round(self.player.game_object.position.x / grid_size) * grid_size;
-        let grid_mid_y = f32::round(self.player.game_object.position.y / grid_size) * grid_size;
+        let grid_mid_x = f32::round(player_position.x / grid_size) * grid_size;
+        let grid_mid_y = f32::round(player_position.y / grid_size) * grid_size;
         let thickness = f32::max(self.camera.zoom, 0.25);
         for x_offset in -line_half_count..=line_half_count {
             let x = grid_mid_x + x_offset as f32 * grid_size - self.camera.position.x;
@@ -125,15 +475,243 @@ impl Game {
         // mq::draw_circle(mq::screen_width() / 2.0, mq::screen_height() / 2.0, 5.0, mq::BLUE);
 
         for enemy in &self.enemies {
-            enemy.game_object.draw(&self.camera);
+            enemy.game_object.draw(&self.camera, self.interpolation);
         }
         for bullet in &self.bullets {
-            bullet.game_object.draw(&self.camera);
+            bullet.draw(&self.camera, self.interpolation);
         }
-        self.home_base.draw(&self.camera);
+        for explosion in &self.explosions {
+            explosion.draw(&self.camera);
+        }
+        self.home_base.draw(&self.camera, self.interpolation);
         for base in &self.bases {
-            base.game_object.draw(&self.camera);
+            base.game_object.draw(&self.camera, self.interpolation);
+        }
+        let is_blinked_out = self.player.game_object.health_status == HealthStatus::Invulnerable
+            && (self.player_invulnerable_time * INVULNERABLE_BLINK_RATE * 2.0) as i32 % 2 == 1;
+        if !is_blinked_out {
+            self.player.game_object.draw(&self.camera, self.interpolation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude as mq;
+
+    use super::*;
+    use crate::explosion::ExplosionStats;
+    use crate::input::IdleInputSource;
+    use crate::simulation::Simulation;
+    use crate::simulation::tests::{add_enemy, add_player_bullet, new_empty_simulation, new_level_1_simulation, new_simulation, teleport};
+
+    fn get_enemy_positions(game: &Game) -> Vec<(f32, f32)> {
+        game.enemies.iter().map(|enemy| enemy.game_object.position.into()).collect()
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_world() {
+        let mut first = new_level_1_simulation(42, Box::new(IdleInputSource));
+        let mut second = new_level_1_simulation(42, Box::new(IdleInputSource));
+        let other_seed = new_level_1_simulation(43, Box::new(IdleInputSource));
+        assert_eq!(get_enemy_positions(&first.game), get_enemy_positions(&second.game));
+        assert_ne!(get_enemy_positions(&first.game), get_enemy_positions(&other_seed.game));
+        // And it plays out the same way
+        first.run(240, TICK_DURATION);
+        second.run(240, TICK_DURATION);
+        assert_eq!(get_enemy_positions(&first.game), get_enemy_positions(&second.game));
+    }
+
+    #[test]
+    fn bullets_hit_enemies_in_neighbouring_grid_cells() {
+        let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let game = &mut simulation.game;
+        // Straddling the cell boundary at x = 2000, with the bullet coming from the cell to the left
+        let target = add_enemy(game, "Slow", mq::vec2(2010.0, 0.0));
+        let bystander = add_enemy(game, "Slow", mq::vec2(2010.0, 400.0));
+        add_player_bullet(game, mq::vec2(1920.0, 0.0), mq::vec2(300.0, 0.0));
+        simulation.run(60, TICK_DURATION);
+        let ids: Vec<_> = simulation.game.enemies.iter().map(|enemy| enemy.id).collect();
+        assert_eq!(ids, vec![bystander], "{:?} should have been shot", target);
+        assert!(simulation.game.bullets.is_empty());
+    }
+
+    #[test]
+    fn fast_bullets_hit_what_they_pass_through_between_ticks() {
+        let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let game = &mut simulation.game;
+        add_enemy(game, "Slow", mq::vec2(2000.0, 0.0)); // 70 across
+        // 250 a tick, so it's well short of the ship before the tick and well past it after
+        add_player_bullet(game, mq::vec2(1900.0, 0.0), mq::vec2(250.0 * TICK_RATE, 0.0));
+        simulation.run(1, TICK_DURATION);
+        assert!(simulation.game.enemies.is_empty());
+        assert!(simulation.game.bullets.is_empty(), "the bullet is used up by the hit");
+    }
+
+    #[test]
+    fn beams_hit_ships_that_cross_them_during_the_tick() {
+        let simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let mut beam = Bullet::new_beam(&simulation.game.player, &Default::default(), 200.0, 1.0);
+        (beam.game_object.previous_position, beam.game_object.position) = (mq::vec2(0.0, 0.0), mq::vec2(200.0, 0.0));
+        // Both cross the beam's line within the tick, one over the beam and one past its end
+        let crossing = |x: f32| GameObject {
+            previous_position: mq::vec2(x, -30.0),
+            position: mq::vec2(x, 30.0),
+            radius: 10.0,
+            ..GameObject::default()
+        };
+        let (mut over, mut past) = (crossing(150.0), crossing(250.0));
+        beam.hit_if_overlapping(&mut over);
+        beam.hit_if_overlapping(&mut past);
+        assert_eq!(over.health_status, HealthStatus::Dead);
+        assert_eq!(past.health_status, HealthStatus::Alive);
+    }
+
+    #[test]
+    fn far_bullets_run_out_without_shooting_anything_down() {
+        let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let game = &mut simulation.game;
+        let far_away = mq::vec2(5000.0, 0.0);
+        add_enemy(game, "Slow", far_away);
+        let mut missile = Bullet::new_missile(&game.enemies[0], &Default::default(), 0.5, 1.0, 1.0, 2.5, 800.0);
+        missile.id = game.entity_ids.allocate();
+        teleport(&mut missile.game_object, far_away);
+        game.bullets.push(missile);
+        add_player_bullet(game, far_away, mq::vec2(1.0, 0.0));
+        simulation.run(1, TICK_DURATION);
+        assert_eq!(simulation.game.bullets.len(), 2, "the player's bullet is sitting on the missile, but too far away to hit it");
+        simulation.run(TICK_RATE as u64 / 2, TICK_DURATION);
+        assert_eq!(simulation.game.bullets.len(), 1, "the missile has run out");
+        assert_eq!(simulation.game.bullets[0].team, Team::Player);
+    }
+
+    #[test]
+    fn far_explosive_bullets_run_out_without_going_off() {
+        let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let game = &mut simulation.game;
+        let id = add_player_bullet(game, mq::vec2(5000.0, 0.0), mq::vec2(1.0, 0.0));
+        let bullet = game.bullets.iter_mut().find(|bullet| bullet.id == id).expect("just added");
+        bullet.lifetime_remaining = TICK_DURATION / 2.0;
+        bullet.explosion = Some(ExplosionStats { radius: 100.0, damage: 10.0, knockback: 0.0 });
+        simulation.run(1, TICK_DURATION);
+        assert!(simulation.game.bullets.is_empty(), "the bullet has run out");
+        assert!(simulation.game.explosions.is_empty());
+    }
+
+    #[test]
+    fn missiles_turn_toward_enemies_in_range() {
+        let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+        let game = &mut simulation.game;
+        add_enemy(game, "Slow", mq::vec2(2000.0, 300.0));
+        let mut missile = Bullet::new_missile(&game.player, &Default::default(), 4.0, 250.0, 1.0, 2.5, 800.0);
+        missile.id = game.entity_ids.allocate();
+        teleport(&mut missile.game_object, mq::vec2(2000.0, 0.0));
+        game.bullets.push(missile); // Fired along +x, so it only hits by turning
+        simulation.run(240, TICK_DURATION);
+        assert!(simulation.game.enemies.is_empty());
+    }
+
+    #[test]
+    fn enemies_only_bounce_apart_in_levels_that_turn_it_on() {
+        for enemy_collisions in [false, true] {
+            let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+            let game = &mut simulation.game;
+            assert!(!game.enemy_collisions, "off unless a level asks for it");
+            game.enemy_collisions = enemy_collisions;
+            // Too far from the player to go after it, so they'd sit still otherwise
+            add_enemy(game, "Slow", mq::vec2(2000.0, 0.0));
+            add_enemy(game, "Slow", mq::vec2(2020.0, 0.0));
+            simulation.run(60, TICK_DURATION);
+            let [first, second] = [0, 1].map(|index| simulation.game.enemies[index].game_object.position);
+            let radii = 2.0 * simulation.game.enemies[0].game_object.radius;
+            assert_eq!(first.distance(second) >= radii, enemy_collisions, "{} apart", first.distance(second));
+        }
+    }
+
+    #[test]
+    fn aimed_enemies_only_hold_fire_out_of_range_when_there_are_factions() {
+        for enemies_fight_each_other in [false, true] {
+            let mut simulation = new_empty_simulation(Box::new(IdleInputSource));
+            let game = &mut simulation.game;
+            game.enemies_fight_each_other = enemies_fight_each_other;
+            // Pointed straight at the player, twice as far away as its missiles fly
+            add_enemy(game, "MissileBoat", mq::vec2(2160.0, 0.0));
+            game.enemies[0].game_object.direction = Direction::new(PI);
+            simulation.run(1, TICK_DURATION);
+            assert_eq!(simulation.game.bullets.is_empty(), enemies_fight_each_other);
         }
-        self.player.game_object.draw(&self.camera);
+    }
+
+    /// Two bases off to the right of home, far enough away that their guards don't notice the player there
+    fn new_two_base_simulation(reaction: &str) -> Simulation {
+        let level = format!(r#"(name: "Two bases", bases: [
+            (position: (1500.0, 0.0), enemy_counts: {{"HighFriction": 5}}, optimal_distance: 60.0, max_distance: 300.0, on_collected: {}),
+            (position: (1500.0, 800.0), enemy_counts: {{"HighFriction": 5}}, optimal_distance: 60.0, max_distance: 300.0),
+        ])"#, reaction);
+        new_simulation(&level, Box::new(IdleInputSource))
+    }
+
+    /// How far the guards of `bases[base_index]` are from `point`, on average
+    fn get_guard_distance(game: &Game, base_index: usize, point: mq::Vec2) -> f32 {
+        let guards = &game.bases[base_index].guards;
+        let distances: Vec<f32> = guards.iter()
+            .filter_map(|id| game.enemies.binary_search_by_key(id, |enemy| enemy.id).ok())
+            .map(|index| game.enemies[index].game_object.position.distance(point))
+            .collect();
+        assert!(!distances.is_empty(), "all the guards are gone");
+        distances.iter().sum::<f32>() / distances.len() as f32
+    }
+
+    /// Picks up the first base with a player that can't be hurt, so it can sit there while the guards react
+    fn collect_first_base(simulation: &mut Simulation) {
+        let game = &mut simulation.game;
+        teleport(&mut game.player.game_object, game.bases[0].origin);
+        game.player.game_object.health_status = HealthStatus::Invulnerable;
+        game.player_invulnerable_time = f32::INFINITY;
+        simulation.run(1, TICK_DURATION);
+        assert!(simulation.game.bases[0].collected);
+    }
+
+    /// Kills the player, so it respawns at home and the base it was carrying goes back
+    fn kill_player(simulation: &mut Simulation) {
+        simulation.game.player.game_object.health_status = HealthStatus::Dead;
+        simulation.run(1, TICK_DURATION);
+        assert!(!simulation.game.bases[0].collected);
+    }
+
+    #[test]
+    fn guards_chase_whoever_carries_their_base() {
+        let mut simulation = new_two_base_simulation("Chase");
+        collect_first_base(&mut simulation);
+        let carrier_position = mq::vec2(1500.0, -1500.0);
+        teleport(&mut simulation.game.player.game_object, carrier_position);
+        let start = get_guard_distance(&simulation.game, 0, carrier_position);
+        simulation.run(5 * TICK_RATE as u64, TICK_DURATION);
+        assert!(get_guard_distance(&simulation.game, 0, carrier_position) < start - 500.0);
+    }
+
+    #[test]
+    fn scattered_guards_come_back_when_their_base_does() {
+        let mut simulation = new_two_base_simulation("Scatter");
+        let origin = simulation.game.bases[0].origin;
+        collect_first_base(&mut simulation);
+        simulation.run(3 * TICK_RATE as u64, TICK_DURATION);
+        let scattered = get_guard_distance(&simulation.game, 0, origin);
+        assert!(scattered > 200.0, "only got {} away", scattered);
+        kill_player(&mut simulation);
+        simulation.run(10 * TICK_RATE as u64, TICK_DURATION);
+        assert!(get_guard_distance(&simulation.game, 0, origin) < 150.0);
+    }
+
+    #[test]
+    fn guards_fall_back_to_the_next_base_and_return_with_their_own() {
+        let mut simulation = new_two_base_simulation("FallBack");
+        let (origin, fallback) = (simulation.game.bases[0].origin, simulation.game.bases[1].origin);
+        collect_first_base(&mut simulation);
+        simulation.run(10 * TICK_RATE as u64, TICK_DURATION);
+        assert!(get_guard_distance(&simulation.game, 0, fallback) < 150.0);
+        kill_player(&mut simulation);
+        simulation.run(10 * TICK_RATE as u64, TICK_DURATION);
+        assert!(get_guard_distance(&simulation.game, 0, origin) < 150.0);
     }
 }
@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+/// Hit points plus an optional shield that soaks up damage first and recharges after a delay.
+#[derive(Debug, Clone)]
+pub struct Health {
+    pub hit_points: f32,
+    pub max_hit_points: f32,
+    pub shield: f32,
+    pub max_shield: f32,
+    pub shield_regen_rate: f32,  // Per second
+    pub shield_regen_delay: f32, // Seconds without damage before the shield starts recharging
+    pub time_since_damage: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShieldStats {
+    pub max_shield: f32,
+    pub regen_rate: f32,
+    pub regen_delay: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new(1.0)
+    }
+}
+
+impl Health {
+    pub fn new(max_hit_points: f32) -> Self {
+        Health {
+            hit_points: max_hit_points, max_hit_points,
+            shield: 0.0, max_shield: 0.0, shield_regen_rate: 0.0, shield_regen_delay: 0.0,
+            time_since_damage: 0.0,
+        }
+    }
+
+    pub fn with_shield(mut self, stats: &ShieldStats) -> Self {
+        self.shield = stats.max_shield;
+        self.max_shield = stats.max_shield;
+        self.shield_regen_rate = stats.regen_rate;
+        self.shield_regen_delay = stats.regen_delay;
+        self
+    }
+
+    pub fn take_damage(&mut self, damage: f32) {
+        let absorbed = damage.min(self.shield);
+        self.shield -= absorbed;
+        self.hit_points -= damage - absorbed;
+        self.time_since_damage = 0.0;
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.hit_points <= 0.0
+    }
+
+    pub fn update(&mut self, delta_t: f32) {
+        self.time_since_damage += delta_t;
+        if self.time_since_damage >= self.shield_regen_delay {
+            self.shield = (self.shield + self.shield_regen_rate * delta_t).min(self.max_shield);
+        }
+    }
+}
@@ -4,13 +4,3 @@ pub enum HealthStatus {
     Dead,
     Invulnerable,
 }
-
-impl HealthStatus {
-    pub fn kill_if_alive(status: Self) -> Self {
-        if status == HealthStatus::Alive {
-            HealthStatus::Dead
-        } else {
-            status
-        }
-    }
-}
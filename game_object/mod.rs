@@ -1,12 +1,17 @@
 pub mod draw_shape;
 pub mod health_status;
+pub mod health;
 
 use macroquad::prelude as mq;
 use crate::prelude::*;
 
 use health_status::HealthStatus;
+use health::Health;
 use draw_shape::{DrawShape, ShapeType};
 
+const RAM_MIN_DAMAGE: f32 = 1.0;
+const RAM_DAMAGE_PER_SPEED: f32 = 0.01; // Extra damage per unit/s of closing speed
+
 #[derive(Debug, Clone)]
 pub struct GameObject {
     pub position: mq::Vec2,
@@ -17,6 +22,8 @@ pub struct GameObject {
     pub direction: Direction,
     pub draw_shapes: Vec<DrawShape>,
     pub health_status: HealthStatus,
+    pub health: Health,
+    pub previous_position: mq::Vec2, // Position at the start of the last tick, for render interpolation
 }
 
 impl Default for GameObject {
@@ -24,16 +31,17 @@ impl Default for GameObject {
         GameObject {
             position: mq::Vec2::ZERO, velocity: mq::Vec2::ZERO, radius: 1.0,
             friction_constant: 0.0, friction_multiplier: 1.0, direction: Direction::new(0.0),
-            draw_shapes: vec![], health_status: HealthStatus::Alive,
+            draw_shapes: vec![], health_status: HealthStatus::Alive, health: Health::default(),
+            previous_position: mq::Vec2::ZERO,
         }
     }
 }
 
 
 impl GameObject {
-    fn randomize_position(&mut self, min_distance: f32, max_distance: f32) {
-        let direction = mq::rand::gen_range(-PI, PI);
-        let square_distance = mq::rand::gen_range(min_distance * min_distance, max_distance * max_distance);
+    fn randomize_position(&mut self, min_distance: f32, max_distance: f32, rng: &mut SeededRng) {
+        let direction = rng.gen_range(-PI, PI);
+        let square_distance = rng.gen_range(min_distance * min_distance, max_distance * max_distance);
         let distance = f32::sqrt(square_distance);
         self.position = distance * mq::Vec2::from_angle(direction);
         self.direction = Direction::new(direction);
@@ -50,11 +58,17 @@ impl GameObject {
         let average_velocity = (self.velocity + new_velocity) / 2.0;
         self.position += average_velocity * delta_t;
         self.velocity = new_velocity;
+        self.health.update(delta_t);
+    }
+
+    /// `interpolation` is how far the render time is between the previous tick and the current one, from 0 to 1
+    pub fn get_draw_position(&self, interpolation: f32) -> mq::Vec2 {
+        self.previous_position.lerp(self.position, interpolation)
     }
 
-    pub fn draw(&self, camera: &Camera) {
+    pub fn draw(&self, camera: &Camera, interpolation: f32) {
         for shape in &self.draw_shapes {
-            let relative_pos = (self.position - camera.position) * camera.zoom;
+            let relative_pos = (self.get_draw_position(interpolation) - camera.position) * camera.zoom;
             let x = relative_pos.x;
             let y = relative_pos.y;
             let thickness = f32::max(1.0, shape.thickness * camera.zoom);
@@ -76,6 +90,25 @@ impl GameObject {
                 }
             }
         }
+        self.draw_health(camera, interpolation);
+    }
+
+    /// A shield ring that fades as the shield drains, and a hit point bar once damaged
+    fn draw_health(&self, camera: &Camera, interpolation: f32) {
+        let relative_pos = (self.get_draw_position(interpolation) - camera.position) * camera.zoom;
+        let radius = self.radius * camera.zoom;
+        if self.health.shield > 0.0 {
+            let mut color = mq::SKYBLUE;
+            color.a = self.health.shield / self.health.max_shield;
+            mq::draw_circle_lines(relative_pos.x, relative_pos.y, radius * 1.3, f32::max(1.0, camera.zoom), color);
+        }
+        if self.health.hit_points < self.health.max_hit_points {
+            let width = 2.0 * radius;
+            let fraction = (self.health.hit_points / self.health.max_hit_points).max(0.0);
+            let y = relative_pos.y - radius * 1.5;
+            mq::draw_line(relative_pos.x - radius, y, relative_pos.x + radius, y, 3.0, mq::DARKGRAY);
+            mq::draw_line(relative_pos.x - radius, y, relative_pos.x - radius + width * fraction, y, 3.0, mq::LIME);
+        }
     }
 
     pub fn is_overlapping(x: &Self, y: &Self) -> bool {
@@ -83,10 +116,118 @@ impl GameObject {
         distance <= x.radius + y.radius
     }
 
-    pub fn kill_if_overlapping(x: &mut Self, y: &mut Self) {
-        if GameObject::is_overlapping(x, y) {
-            x.health_status = HealthStatus::kill_if_alive(x.health_status);
-            y.health_status = HealthStatus::kill_if_alive(y.health_status);
+    /// Whether the two objects touched at any point during the last tick, assuming both moved in straight lines
+    /// from `previous_position` to `position`. Unlike `is_overlapping`, fast objects can't pass through each other between ticks.
+    pub fn is_overlapping_swept(x: &Self, y: &Self) -> bool {
+        GameObject::get_closest_offset(x, y).length() <= x.radius + y.radius
+    }
+
+    /// Whether `target` touched the line from `start` to `end`, `line_radius` thick, at any point during the last tick.
+    /// Unlike in `is_overlapping_swept`, the line stays put for the whole tick, as beams do.
+    pub fn is_touching_line_swept(target: &Self, start: mq::Vec2, end: mq::Vec2, line_radius: f32) -> bool {
+        get_segment_distance(start, end, target.previous_position, target.position) <= line_radius + target.radius
+    }
+
+    /// `x`'s position relative to `y` at the moment during the last tick when they were closest
+    fn get_closest_offset(x: &Self, y: &Self) -> mq::Vec2 {
+        let start_offset = x.previous_position - y.previous_position;
+        let relative_movement = (x.position - x.previous_position) - (y.position - y.previous_position);
+        let movement_length_squared = relative_movement.length_squared();
+        let closest_time = if movement_length_squared > 0.0 {
+            (-start_offset.dot(relative_movement) / movement_length_squared).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        start_offset + relative_movement * closest_time
+    }
+
+    /// A circle containing the whole path the object covered during the last tick, for broad-phase queries
+    pub fn get_swept_bounds(&self) -> (mq::Vec2, f32) {
+        let middle = (self.previous_position + self.position) / 2.0;
+        (middle, self.radius + self.previous_position.distance(self.position) / 2.0)
+    }
+
+    /// Takes damage unless invulnerable, and dies once hit points run out
+    pub fn apply_damage(&mut self, damage: f32) {
+        if self.health_status != HealthStatus::Alive {
+            return;
         }
+        self.health.take_damage(damage);
+        if self.health.is_depleted() {
+            self.health_status = HealthStatus::Dead;
+        }
+    }
+
+    /// Damages both objects by how fast they were closing on each other, then cancels that closing speed,
+    /// sharing it by mass (radius squared), so one collision doesn't keep dealing damage every tick
+    pub fn ram_if_overlapping(x: &mut Self, y: &mut Self) {
+        let closest_offset = GameObject::get_closest_offset(x, y);
+        if closest_offset.length() > x.radius + y.radius {
+            return;
+        }
+        let normal = -closest_offset.normalize_or_zero(); // From x towards y
+        let closing_speed = (x.velocity - y.velocity).dot(normal);
+        if closing_speed <= 0.0 {
+            return;
+        }
+        let damage = RAM_MIN_DAMAGE + RAM_DAMAGE_PER_SPEED * closing_speed;
+        x.apply_damage(damage);
+        y.apply_damage(damage);
+        let x_mass = x.radius * x.radius;
+        let y_mass = y.radius * y.radius;
+        let impulse = closing_speed * x_mass * y_mass / (x_mass + y_mass);
+        x.velocity -= normal * impulse / x_mass;
+        y.velocity += normal * impulse / y_mass;
     }
+
+    /// Pushes overlapping objects apart and bounces them off each other elastically, sharing both by mass (radius squared).
+    /// Deals no damage, unlike `ram_if_overlapping`.
+    pub fn bounce_if_overlapping(x: &mut Self, y: &mut Self) {
+        let offset = y.position - x.position;
+        let distance = offset.length();
+        let overlap = x.radius + y.radius - distance;
+        if overlap <= 0.0 {
+            return;
+        }
+        let normal = if distance > 0.0 { offset / distance } else { mq::Vec2::X }; // From x towards y
+        let x_mass = x.radius * x.radius;
+        let y_mass = y.radius * y.radius;
+        let total_mass = x_mass + y_mass;
+        x.position -= normal * overlap * y_mass / total_mass;
+        y.position += normal * overlap * x_mass / total_mass;
+        let closing_speed = (x.velocity - y.velocity).dot(normal);
+        if closing_speed <= 0.0 {
+            return;
+        }
+        let impulse = 2.0 * closing_speed * x_mass * y_mass / total_mass;
+        x.velocity -= normal * impulse / x_mass;
+        y.velocity += normal * impulse / y_mass;
+    }
+}
+
+/// Shortest distance between the segments `a_start`-`a_end` and `b_start`-`b_end`
+fn get_segment_distance(a_start: mq::Vec2, a_end: mq::Vec2, b_start: mq::Vec2, b_end: mq::Vec2) -> f32 {
+    let (a, b) = (a_end - a_start, b_end - b_start);
+    let denominator = a.perp_dot(b);
+    if denominator != 0.0 {
+        let a_fraction = (b_start - a_start).perp_dot(b) / denominator;
+        let b_fraction = (b_start - a_start).perp_dot(a) / denominator;
+        if (0.0..=1.0).contains(&a_fraction) && (0.0..=1.0).contains(&b_fraction) {
+            return 0.0; // They cross
+        }
+    }
+    // Otherwise the closest point is at one of the ends
+    [
+        get_point_to_segment_distance(a_start, b_start, b_end),
+        get_point_to_segment_distance(a_end, b_start, b_end),
+        get_point_to_segment_distance(b_start, a_start, a_end),
+        get_point_to_segment_distance(b_end, a_start, a_end),
+    ].into_iter().fold(f32::INFINITY, f32::min)
+}
+
+fn get_point_to_segment_distance(point: mq::Vec2, start: mq::Vec2, end: mq::Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    let fraction = if length_squared > 0.0 { ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
+    point.distance(start + segment * fraction)
 }
@@ -0,0 +1,73 @@
+use macroquad::prelude as mq;
+
+use crate::prelude::*;
+
+use crate::keybinds;
+
+/// A snapshot of everything the player can press during one tick.
+/// Controllers read this from `Game::input` instead of querying macroquad directly,
+/// so a `Game` can be stepped without a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    pub thrust_key: bool,
+    pub left_key: bool,
+    pub right_key: bool,
+    pub fire_key: bool,
+    pub restart_key: bool,
+    pub left_mouse: bool,
+    pub right_mouse: bool,
+    pub mouse_offset: mq::Vec2, // Relative to the middle of the screen
+    pub buy_upgrade: Option<UpgradeKind>,
+}
+
+impl PlayerInput {
+    pub fn from_macroquad() -> Self {
+        let middle = mq::vec2(mq::screen_width(), mq::screen_height()) / 2.0;
+        let mouse_pos = mq::vec2(mq::mouse_position().0, mq::mouse_position().1);
+        PlayerInput {
+            thrust_key: mq::is_key_down(keybinds::THRUST_KEY),
+            left_key: mq::is_key_down(keybinds::LEFT_KEY),
+            right_key: mq::is_key_down(keybinds::RIGHT_KEY),
+            fire_key: mq::is_key_down(keybinds::FIRE_KEY),
+            restart_key: mq::is_key_pressed(keybinds::RESTART_KEY),
+            left_mouse: mq::is_mouse_button_down(mq::MouseButton::Left),
+            right_mouse: mq::is_mouse_button_down(mq::MouseButton::Right),
+            mouse_offset: mouse_pos - middle,
+            buy_upgrade: keybinds::UPGRADE_KEYS.iter().position(|&key| mq::is_key_down(key)).map(|index| UpgradeKind::ALL[index]),
+        }
+    }
+}
+
+pub trait InputSource {
+    fn next_input(&mut self, game: &Game) -> PlayerInput;
+
+    /// Whether the source has no more input to give, e.g. a replay that has reached its end
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+/// Reads the live keyboard and mouse. Needs a macroquad window.
+pub struct MacroquadInputSource;
+
+impl InputSource for MacroquadInputSource {
+    fn next_input(&mut self, _game: &Game) -> PlayerInput {
+        PlayerInput::from_macroquad()
+    }
+}
+
+/// Never presses anything.
+pub struct IdleInputSource;
+
+impl InputSource for IdleInputSource {
+    fn next_input(&mut self, _game: &Game) -> PlayerInput {
+        PlayerInput::default()
+    }
+}
+
+/// Lets a closure drive the player, e.g. a scripted bot in a test.
+impl<F: FnMut(&Game) -> PlayerInput> InputSource for F {
+    fn next_input(&mut self, game: &Game) -> PlayerInput {
+        self(game)
+    }
+}
@@ -5,3 +5,12 @@ pub const LEFT_KEY: mq::KeyCode = mq::KeyCode::A;
 pub const RIGHT_KEY: mq::KeyCode = mq::KeyCode::D;
 pub const FIRE_KEY: mq::KeyCode = mq::KeyCode::Space;
 pub const RESTART_KEY: mq::KeyCode = mq::KeyCode::Enter;
+pub const MENU_KEY: mq::KeyCode = mq::KeyCode::Escape;
+pub const MENU_UP_KEY: mq::KeyCode = mq::KeyCode::Up;
+pub const MENU_DOWN_KEY: mq::KeyCode = mq::KeyCode::Down;
+pub const MINIMAP_KEY: mq::KeyCode = mq::KeyCode::M;
+// One per upgrade, in the shop's order
+pub const UPGRADE_KEYS: [mq::KeyCode; 7] = [
+    mq::KeyCode::Key1, mq::KeyCode::Key2, mq::KeyCode::Key3, mq::KeyCode::Key4,
+    mq::KeyCode::Key5, mq::KeyCode::Key6, mq::KeyCode::Key7,
+];
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+use crate::base::GuardReaction;
+
+// Compiled in as well, so replays recorded before levels were files still have their level
+const BUILT_IN_LEVEL_1: &str = include_str!("../levels/level_1.ron");
+
+/// A level loaded from a RON file, so bases and spawn regions can be changed without recompiling.
+/// See `levels/level_1.ron` for the format.
+#[derive(Clone)]
+pub struct LevelFile {
+    pub name: String,
+    bases: Vec<Base>,
+    spawn_regions: Vec<SpawnRegion>,
+    enemy_collisions: bool,
+    team_rules: TeamRules,
+    content_hash: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelData {
+    name: String,
+    bases: Vec<BaseData>,
+    #[serde(default)]
+    spawn_regions: Vec<SpawnRegionData>,
+    #[serde(default)]
+    enemy_collisions: bool,
+    #[serde(default)]
+    team_rules: TeamRules,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaseData {
+    position: (f32, f32),
+    enemy_counts: HashMap<String, u32>,
+    optimal_distance: f32,
+    max_distance: f32,
+    #[serde(default = "default_team")]
+    team: Team,
+    #[serde(default)]
+    on_collected: GuardReaction,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpawnRegionData {
+    enemy_counts: HashMap<String, u32>,
+    min_distance: f32,
+    max_distance: f32,
+    #[serde(default = "default_team")]
+    team: Team,
+}
+
+fn default_team() -> Team {
+    Team::Hostile
+}
+
+#[derive(Debug)]
+pub enum LevelFileError {
+    Io(io::Error),
+    Syntax(ron::error::SpannedError),
+    UnknownEnemyType { location: String, name: String, known: Vec<String> },
+    InvalidValue { location: String, message: String },
+}
+
+impl fmt::Display for LevelFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelFileError::Io(error) => write!(f, "{}", error),
+            LevelFileError::Syntax(error) => write!(f, "{}", error),
+            LevelFileError::UnknownEnemyType { location, name, known } => {
+                write!(f, "{}: unknown enemy type `{}`, expected one of {}", location, name, known.join(", "))
+            }
+            LevelFileError::InvalidValue { location, message } => write!(f, "{}: {}", location, message),
+        }
+    }
+}
+
+impl From<io::Error> for LevelFileError {
+    fn from(error: io::Error) -> Self {
+        LevelFileError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelFileError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        LevelFileError::Syntax(error)
+    }
+}
+
+impl LevelFile {
+    /// Enemy types are checked against `archetypes`, so a typo is reported here rather than silently spawning nothing
+    pub fn load(path: impl AsRef<Path>, archetypes: &EnemyArchetypes) -> Result<Self, LevelFileError> {
+        let text = fs::read_to_string(path)?;
+        LevelFile::parse(&text, archetypes)
+    }
+
+    /// `levels/level_1.ron` as it was when the game was built
+    pub fn built_in(archetypes: &EnemyArchetypes) -> Result<Self, LevelFileError> {
+        LevelFile::parse(BUILT_IN_LEVEL_1, archetypes)
+    }
+
+    pub fn parse(text: &str, archetypes: &EnemyArchetypes) -> Result<Self, LevelFileError> {
+        let data: LevelData = ron::from_str(text)?;
+        let mut bases = vec![];
+        for (index, base) in data.bases.into_iter().enumerate() {
+            let location = format!("bases[{}]", index);
+            let (x, y) = base.position;
+            check_finite(&location, "position", x)?;
+            check_finite(&location, "position", y)?;
+            check_not_negative(&location, "optimal_distance", base.optimal_distance)?;
+            check_not_negative(&location, "max_distance", base.max_distance)?;
+            if base.optimal_distance > base.max_distance {
+                return Err(LevelFileError::InvalidValue {
+                    location,
+                    message: format!("optimal_distance {} is greater than max_distance {}", base.optimal_distance, base.max_distance),
+                });
+            }
+            check_enemy_team(&location, base.team)?;
+            let enemy_counts = parse_enemy_counts(&location, base.enemy_counts, archetypes)?;
+            bases.push(Base::new(mq::Vec2::new(x, y), enemy_counts, base.optimal_distance, base.max_distance).with_team(base.team).with_guard_reaction(base.on_collected));
+        }
+        let mut spawn_regions = vec![];
+        for (index, spawn_region) in data.spawn_regions.into_iter().enumerate() {
+            let location = format!("spawn_regions[{}]", index);
+            check_not_negative(&location, "min_distance", spawn_region.min_distance)?;
+            check_not_negative(&location, "max_distance", spawn_region.max_distance)?;
+            if spawn_region.min_distance > spawn_region.max_distance {
+                return Err(LevelFileError::InvalidValue {
+                    location,
+                    message: format!("min_distance {} is greater than max_distance {}", spawn_region.min_distance, spawn_region.max_distance),
+                });
+            }
+            check_enemy_team(&location, spawn_region.team)?;
+            let enemy_counts = parse_enemy_counts(&location, spawn_region.enemy_counts, archetypes)?;
+            spawn_regions.push(SpawnRegion::new(enemy_counts, spawn_region.min_distance, spawn_region.max_distance).with_team(spawn_region.team));
+        }
+        let content_hash = hash_text(text);
+        Ok(LevelFile { name: data.name, bases, spawn_regions, enemy_collisions: data.enemy_collisions, team_rules: data.team_rules, content_hash })
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is guaranteed to give the same value in every build, so saved hashes stay valid
+fn hash_text(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn parse_enemy_counts(location: &str, enemy_counts: HashMap<String, u32>, archetypes: &EnemyArchetypes) -> Result<HashMap<EnemyShipType, u32>, LevelFileError> {
+    let mut parsed = HashMap::new();
+    for (name, count) in enemy_counts {
+        let enemy_type = EnemyShipType::from_name(&name);
+        if !archetypes.contains(&enemy_type) {
+            let known = archetypes.get_names().into_iter().map(String::from).collect();
+            return Err(LevelFileError::UnknownEnemyType { location: location.to_string(), name, known });
+        }
+        parsed.insert(enemy_type, count);
+    }
+    Ok(parsed)
+}
+
+fn check_finite(location: &str, field: &str, value: f32) -> Result<(), LevelFileError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(LevelFileError::InvalidValue { location: location.to_string(), message: format!("{} must be a finite number", field) })
+    }
+}
+
+fn check_not_negative(location: &str, field: &str, value: f32) -> Result<(), LevelFileError> {
+    check_finite(location, field, value)?;
+    if value >= 0.0 {
+        Ok(())
+    } else {
+        Err(LevelFileError::InvalidValue { location: location.to_string(), message: format!("{} must not be negative, got {}", field, value) })
+    }
+}
+
+/// Enemies fight for the other sides; siding a faction with the player is what `team_rules` alliances are for
+fn check_enemy_team(location: &str, team: Team) -> Result<(), LevelFileError> {
+    if team == Team::Player {
+        Err(LevelFileError::InvalidValue { location: location.to_string(), message: "team must be Hostile or a Faction, not Player".to_string() })
+    } else {
+        Ok(())
+    }
+}
+
+impl Level for LevelFile {
+    fn get_bases(&self) -> Vec<Base> {
+        self.bases.clone()
+    }
+    fn get_spawn_regions(&self) -> Vec<SpawnRegion> {
+        self.spawn_regions.clone()
+    }
+    fn has_enemy_collisions(&self) -> bool {
+        self.enemy_collisions
+    }
+    fn get_team_rules(&self) -> TeamRules {
+        self.team_rules.clone()
+    }
+    fn get_content_hash(&self) -> u64 {
+        self.content_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_level_1_loads() {
+        let level = LevelFile::built_in(&EnemyArchetypes::default()).expect("the built-in level is valid");
+        assert_eq!(level.name, "Level 1");
+        assert_eq!(level.get_bases().len(), 13);
+        assert!(!level.has_enemy_collisions(), "enemies overlap unless a level turns collisions on");
+    }
+
+    #[test]
+    fn editing_a_level_changes_its_content_hash() {
+        let archetypes = EnemyArchetypes::default();
+        let original = LevelFile::parse(r#"(name: "Hash", bases: [])"#, &archetypes).expect("valid level");
+        let same = LevelFile::parse(r#"(name: "Hash", bases: [])"#, &archetypes).expect("valid level");
+        let edited = LevelFile::parse(r#"(name: "Hash", bases: [], enemy_collisions: true)"#, &archetypes).expect("valid level");
+        assert_eq!(original.get_content_hash(), same.get_content_hash());
+        assert_ne!(original.get_content_hash(), edited.get_content_hash());
+    }
+
+    #[test]
+    fn unknown_enemy_type_is_rejected_when_loading() {
+        let text = r#"(name: "Bad", bases: [(position: (0.0, 0.0), enemy_counts: {"Slwo": 1}, optimal_distance: 50.0, max_distance: 500.0)])"#;
+        let error = LevelFile::parse(text, &EnemyArchetypes::default()).err().expect("there's no archetype called Slwo");
+        assert!(matches!(error, LevelFileError::UnknownEnemyType { ref name, .. } if name == "Slwo"), "{}", error);
+    }
+
+    #[test]
+    fn base_guarding_further_out_than_it_defends_is_rejected() {
+        let text = r#"(name: "Bad", bases: [(position: (0.0, 0.0), enemy_counts: {"Slow": 1}, optimal_distance: 600.0, max_distance: 500.0)])"#;
+        let error = LevelFile::parse(text, &EnemyArchetypes::default()).err().expect("optimal_distance is past max_distance");
+        assert!(matches!(error, LevelFileError::InvalidValue { ref location, .. } if location == "bases[0]"), "{}", error);
+    }
+}
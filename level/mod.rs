@@ -1,8 +1,20 @@
-pub mod level_1;
+pub mod level_file;
 
 use crate::prelude::*;
 
 pub trait Level {
     fn get_bases(&self) -> Vec<Base>;
     fn get_spawn_regions(&self) -> Vec<SpawnRegion>;
+
+    /// Whether enemies bounce off each other rather than passing through
+    fn has_enemy_collisions(&self) -> bool {
+        false
+    }
+
+    fn get_team_rules(&self) -> TeamRules {
+        TeamRules::default()
+    }
+
+    /// Changes whenever the level is edited, so replays can tell they were recorded in a different version of it
+    fn get_content_hash(&self) -> u64;
 }
@@ -3,7 +3,7 @@
 #![allow(clippy::useless_format)]
 
 
-use level::level_1::Level1;
+use ship::archetype::EnemyArchetypes;
 use macroquad::prelude as mq;
 
 mod game;
@@ -15,14 +15,32 @@ mod direction;
 mod game_object;
 mod ship;
 mod spawn_regions;
+mod spatial_grid;
 mod team;
 mod weapon;
 mod level;
 
 mod prelude;
 mod keybinds;
+mod input;
+mod simulation;
+mod rng;
+mod replay;
+mod campaign;
+mod menu;
+mod save;
+mod upgrades;
+mod explosion;
+mod world;
+mod entity;
+mod minimap;
 
-use game_object::GameObject;
+use input::MacroquadInputSource;
+use replay::{Recording, ReplayInputSource};
+use simulation::Simulation;
+use campaign::{Campaign, CampaignLevel, LevelSource};
+use save::SaveData;
+use upgrades::UpgradeKind;
 
 fn window_conf() -> mq::Conf {
     mq::Conf {
@@ -32,59 +50,255 @@ fn window_conf() -> mq::Conf {
     }
 }
 
+const LAST_RUN_REPLAY_PATH: &str = "last_run.replay";
+const ARCHETYPES_PATH: &str = "data/enemy_archetypes.ron";
+const SAVE_PATH: &str = "save.ron";
+
+/// Reads the value following `name` on the command line, e.g. `--seed 1234`
+fn get_arg(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == name)?;
+    args.get(index + 1).cloned()
+}
+
+/// The archetype data file if there is one, otherwise the built-in archetypes
+fn load_archetypes() -> EnemyArchetypes {
+    if !std::path::Path::new(ARCHETYPES_PATH).exists() {
+        return EnemyArchetypes::default();
+    }
+    EnemyArchetypes::load(ARCHETYPES_PATH).unwrap_or_else(|error| {
+        eprintln!("Could not load {}, using the built-in archetypes: {}", ARCHETYPES_PATH, error);
+        EnemyArchetypes::default()
+    })
+}
+
+async fn play_replay(path: &str) {
+    let recording = match Recording::load(path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("Could not load replay {}: {}", path, error);
+            return;
+        }
+    };
+    let archetypes = load_archetypes();
+    let source = match &recording.level_path {
+        Some(path) => LevelSource::File(path.clone()),
+        None => LevelSource::BuiltIn,
+    };
+    let campaign_level = CampaignLevel { name: format!("Replay"), source, bases_to_unlock_next: 0 };
+    let level = match campaign_level.load(&archetypes) {
+        Ok(level) => level,
+        Err(error) => {
+            eprintln!("Could not load the replay's level: {}", error);
+            return;
+        }
+    };
+    match recording.level_hash {
+        Some(hash) if hash != level.get_content_hash() => {
+            eprintln!("The replay's level has been edited since it was recorded, so the replay would play out differently");
+            return;
+        }
+        Some(_) => {}
+        None => eprintln!("The replay doesn't say which version of its level it was recorded in, so it may play out differently"),
+    }
+    let Some(game) = Game::new(level.as_ref(), &archetypes, &recording.upgrades, recording.bases_to_spend, recording.seed) else {
+        return;
+    };
+    let mut simulation = Simulation::new(game, Box::new(ReplayInputSource::new(recording)));
+    let mut show_minimap = true;
+    loop {
+        simulation.game.camera.zoom *= f32::powf(1.1, mq::mouse_wheel().1);
+        simulation.advance(mq::get_frame_time());
+        mq::clear_background(mq::DARKPURPLE);
+        simulation.game.draw();
+        if mq::is_key_pressed(keybinds::MINIMAP_KEY) {
+            show_minimap = !show_minimap;
+        }
+        minimap::draw_objective_arrows(&simulation.game);
+        if show_minimap {
+            minimap::draw_minimap(&simulation.game);
+        }
+        let text = &format!("Replay of seed {}", simulation.game.seed);
+        mq::draw_text(text, 0.0, 48.0, 48.0, mq::LIME);
+        if simulation.game.gameover || simulation.input_source.is_finished() {
+            mq::draw_text("Replay finished, press return to exit", 0.0, 2.0 * 48.0, 48.0, mq::LIME);
+            if mq::is_key_pressed(keybinds::RESTART_KEY) {
+                return;
+            }
+        }
+        mq::next_frame().await
+    }
+}
+
+enum SessionEnd {
+    OutOfLives,
+    ReturnedHome { bases_collected: u32, time: f32 },
+    Quit,
+}
+
+/// Shows `text` until return is pressed
+async fn show_message(text: &str) {
+    mq::next_frame().await; // So a press that closed the last screen doesn't close this one too
+    loop {
+        mq::clear_background(mq::PURPLE);
+        for (index, line) in text.lines().enumerate() {
+            mq::draw_text(line, 0.0, (index as f32 + 1.0) * 48.0, 48.0, mq::LIME);
+        }
+        if mq::is_key_pressed(keybinds::RESTART_KEY) {
+            return;
+        }
+        mq::next_frame().await
+    }
+}
+
+/// Lists the upgrades with the number key that buys each one
+fn draw_shop(game: &Game) {
+    mq::draw_text("Upgrades (number keys to buy)", 0.0, 6.0 * 48.0, 32.0, mq::LIME);
+    for (index, &kind) in UpgradeKind::ALL.iter().enumerate() {
+        let level = game.upgrades.get_level(kind);
+        let (cost, color) = match game.upgrades.get_next_cost(kind) {
+            Some(cost) if cost <= game.bases_to_spend => (format!("{} bases", cost), mq::LIME),
+            Some(cost) => (format!("{} bases", cost), mq::GRAY),
+            None => (format!("maxed"), mq::GRAY),
+        };
+        let text = &format!("{}  {}  level {}  {}", index + 1, kind.name(), level, cost);
+        mq::draw_text(text, 0.0, 6.0 * 48.0 + (index as f32 + 1.0) * 32.0, 32.0, color);
+    }
+}
+
+/// Plays `campaign_level` from the start until the player runs out of lives, brings bases home or quits to the menu.
+/// Also returns how many times the player died along the way
+async fn play_session(campaign_level: &CampaignLevel, save: &mut SaveData, seed: u64) -> (SessionEnd, u32) {
+    // Loaded for every session so level and archetype edits show up without relaunching
+    let archetypes = load_archetypes();
+    let level = match campaign_level.load(&archetypes) {
+        Ok(level) => level,
+        Err(error) => {
+            show_message(&format!("Could not load {}:\n{}\nPress return to continue", campaign_level.name, error)).await;
+            return (SessionEnd::Quit, 0);
+        }
+    };
+    let best_bases = save.progress.get_best(&campaign_level.name);
+    let Some(game) = Game::new(level.as_ref(), &archetypes, &save.upgrades, save.get_bases_to_spend(), seed) else {
+        show_message("You win\nPress return to restart").await;
+        return (SessionEnd::Quit, 0);
+    };
+    let mut simulation = Simulation::new(game, Box::new(MacroquadInputSource));
+    simulation.start_recording(campaign_level.get_path(), level.get_content_hash());
+    simulation.game.camera.zoom = save.settings.zoom;
+    // A press that picked this level or closed the last screen is still down on the first frame, and isn't a request to bank
+    let mut is_first_frame = true;
+    let session_end = loop {
+        simulation.game.camera.zoom *= f32::powf(1.1, mq::mouse_wheel().1);
+        simulation.advance(mq::get_frame_time());
+        let game = &mut simulation.game;
+        mq::clear_background(mq::DARKPURPLE);
+        game.draw();
+        if mq::is_key_pressed(keybinds::MINIMAP_KEY) {
+            save.settings.show_minimap = !save.settings.show_minimap;
+        }
+        minimap::draw_objective_arrows(game);
+        if save.settings.show_minimap {
+            minimap::draw_minimap(game);
+        }
+
+        if game.gameover {
+            break SessionEnd::OutOfLives;
+        }
+        if mq::is_key_pressed(keybinds::MENU_KEY) {
+            break SessionEnd::Quit;
+        }
+        let text = &format!("{} Bases To Spend", game.bases_to_spend);
+        mq::draw_text(text, 0.0, 48.0, 48.0, mq::LIME);
+        let text = &format!("{} / {} Bases Collected", game.collected_base_count, game.bases.len());
+        mq::draw_text(text, 0.0, 2.0 * 48.0, 48.0, mq::LIME);
+        let text = &format!("Lives {}", game.lives);
+        mq::draw_text(text, mq::screen_width() - 160.0, 48.0, 48.0, mq::LIME);
+        let text = &format!("{}, seed {}", campaign_level.name, game.seed);
+        mq::draw_text(text, 0.0, mq::screen_height() - 12.0, 24.0, mq::LIME);
+        if game.is_player_at_home() {
+            let text = &format!("At Home Base");
+            mq::draw_text(text, 0.0, 3.0 * 48.0, 48.0, mq::LIME);
+            if game.collected_base_count > best_bases {
+                let text = &format!("Press return to bank {} more bases to spend", game.collected_base_count - best_bases);
+                mq::draw_text(text, 0.0, 4.0 * 48.0, 48.0, mq::LIME);
+            } else {
+                let text = &format!("Press return to leave");
+                mq::draw_text(text, 0.0, 4.0 * 48.0, 48.0, mq::LIME);
+            }
+            draw_shop(game);
+            // Checked every frame rather than through `game.input`, which frames that run no tick never sample
+            if !is_first_frame && mq::is_key_pressed(keybinds::RESTART_KEY) {
+                break SessionEnd::ReturnedHome { bases_collected: game.collected_base_count, time: game.elapsed_time };
+            }
+        }
+
+        is_first_frame = false;
+        mq::next_frame().await
+    };
+    save.settings.zoom = simulation.game.camera.zoom;
+    save.upgrades = simulation.game.upgrades.clone();
+    if let Some(recording) = &simulation.recording {
+        if let Err(error) = recording.save(LAST_RUN_REPLAY_PATH) {
+            eprintln!("Could not save replay to {}: {}", LAST_RUN_REPLAY_PATH, error);
+        }
+    }
+    (session_end, simulation.game.deaths)
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut player_level = 0.0;
+    if let Some(path) = get_arg("--replay") {
+        play_replay(&path).await;
+        return;
+    }
+    // A fixed seed rebuilds the same world on every restart, for bug reports and fair runs
+    let seed_arg = get_arg("--seed").and_then(|seed| seed.parse::<u64>().ok());
+    // `--level` plays a single level file on its own, for trying out levels while making them
+    let campaign = match get_arg("--level") {
+        Some(path) => Campaign {
+            levels: vec![CampaignLevel { name: path.clone(), source: LevelSource::File(path), bases_to_unlock_next: 0 }],
+        },
+        None => Campaign::default(),
+    };
+    let (mut save, load_failure) = SaveData::load_or_new(SAVE_PATH);
+    // A save left in place couldn't be read, but may be fine, so it's never saved over
+    let can_save = load_failure.as_ref().is_none_or(|failure| failure.moved_to.is_some());
+    if let Some(failure) = &load_failure {
+        eprintln!("Could not load {}: {}", SAVE_PATH, failure.error);
+        let text = match &failure.moved_to {
+            Some(path) => format!("Could not load your save, so it was moved to {}\nand a new one started:\n{}", path.display(), failure.error),
+            None => format!("Could not load your save:\n{}\nPlaying without saving, so it isn't overwritten", failure.error),
+        };
+        show_message(&format!("{}\nPress return to continue", text)).await;
+    }
+    let mut selected = 0;
     loop {
         mq::next_frame().await;
-        let level = Level1;
-        let game = Game::new(level, player_level);
-        if let Some(mut game) = game {
-            loop {
-                game.camera.zoom *= f32::powf(1.1, mq::mouse_wheel().1);
-                game.update(mq::get_frame_time());
-                mq::clear_background(mq::DARKPURPLE);
-                game.draw();
-
-                if game.gameover {
-                    break;
-                }
-                let text = &format!("Player Level {}", player_level);
-                mq::draw_text(text, 0.0, 48.0, 48.0, mq::LIME);
-                let text = &format!("{} / {} Bases Collected", game.collected_base_count, game.bases.len());
-                mq::draw_text(text, 0.0, 2.0 * 48.0, 48.0, mq::LIME);
-                if GameObject::is_overlapping(&game.player.game_object, &game.home_base) {
-                    let text = &format!("At Home Base");
-                    mq::draw_text(text, 0.0, 3.0 * 48.0, 48.0, mq::LIME);
-                    if game.collected_base_count as f32 > player_level {
-                        let text = &format!("Press return to restart and level up to {}", game.collected_base_count);
-                        mq::draw_text(text, 0.0, 4.0 * 48.0, 48.0, mq::LIME);
-                        if mq::is_key_pressed(keybinds::RESTART_KEY) {
-                            player_level = game.collected_base_count as f32;
-                            break;
-                        }
-                    } else {
-                        let text = &format!("Press return to restart");
-                        mq::draw_text(text, 0.0, 4.0 * 48.0, 48.0, mq::LIME);
-                        if mq::is_key_pressed(keybinds::RESTART_KEY) {
-                            break;
-                        }
-                    }
+        if campaign.levels.len() > 1 {
+            selected = menu::select_level(&campaign, &save.progress, selected).await;
+            mq::next_frame().await; // The press that picked the level is still down until the next frame
+        }
+        let campaign_level = &campaign.levels[selected];
+        loop {
+            let seed = seed_arg.unwrap_or_else(|| (macroquad::miniquad::date::now() * 1000.0) as u64);
+            let (session_end, deaths) = play_session(campaign_level, &mut save, seed).await;
+            save.progress.record_deaths(&campaign_level.name, deaths);
+            match session_end {
+                SessionEnd::ReturnedHome { bases_collected, time } => {
+                    save.progress.bank_bases(&campaign_level.name, bases_collected, time);
                 }
-
-
-                mq::next_frame().await
+                SessionEnd::OutOfLives | SessionEnd::Quit => {}
             }
-        } else {
-            loop {
-                mq::clear_background(mq::PURPLE);
-                let text = "You win\nPress return to restart";
-                mq::draw_text(text, 0.0, 48.0, 48.0, mq::LIME);
-                if mq::is_key_down(mq::KeyCode::Enter) {
-                    break;
+            if can_save {
+                if let Err(error) = save.save(SAVE_PATH) {
+                    eprintln!("Could not save to {}: {}", SAVE_PATH, error);
                 }
-                mq::next_frame().await
             }
+            if !matches!(session_end, SessionEnd::OutOfLives) {
+                break;
+            }
+            mq::next_frame().await;
         }
     }
 }
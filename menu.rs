@@ -0,0 +1,41 @@
+use macroquad::prelude as mq;
+
+use crate::campaign::{Campaign, CampaignProgress};
+use crate::keybinds;
+
+/// Shows the level list until the player picks an unlocked level, and returns its index
+pub async fn select_level(campaign: &Campaign, progress: &CampaignProgress, mut selected: usize) -> usize {
+    loop {
+        if mq::is_key_pressed(keybinds::MENU_UP_KEY) && selected > 0 {
+            selected -= 1;
+        }
+        if mq::is_key_pressed(keybinds::MENU_DOWN_KEY) && selected + 1 < campaign.levels.len() {
+            selected += 1;
+        }
+        let unlocked = campaign.is_unlocked(selected, progress);
+        if unlocked && mq::is_key_pressed(keybinds::RESTART_KEY) {
+            return selected;
+        }
+
+        mq::clear_background(mq::DARKPURPLE);
+        mq::draw_text("Select Level", 0.0, 48.0, 48.0, mq::LIME);
+        let text = &format!("{} Bases Banked", progress.get_bases_earned());
+        mq::draw_text(text, 0.0, 2.0 * 48.0, 48.0, mq::LIME);
+        for (index, level) in campaign.levels.iter().enumerate() {
+            let marker = if index == selected { ">" } else { " " };
+            let text = if campaign.is_unlocked(index, progress) {
+                let result = progress.results.get(&level.name).cloned().unwrap_or_default();
+                let time = result.best_time.map_or(String::new(), |time| format!(" in {:.1}s", time));
+                format!("{} {}  (best: {} bases{}, {} deaths)", marker, level.name, result.best_bases_collected, time, result.deaths)
+            } else {
+                let previous = &campaign.levels[index - 1];
+                format!("{} {}  (locked: bring {} bases home from {})", marker, level.name, previous.bases_to_unlock_next, previous.name)
+            };
+            let color = if campaign.is_unlocked(index, progress) { mq::LIME } else { mq::GRAY };
+            mq::draw_text(&text, 0.0, (index as f32 + 4.0) * 48.0, 48.0, color);
+        }
+        let text = "Up/down to choose, return to play";
+        mq::draw_text(text, 0.0, mq::screen_height() - 12.0, 24.0, mq::LIME);
+        mq::next_frame().await
+    }
+}
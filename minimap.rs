@@ -0,0 +1,83 @@
+use macroquad::prelude as mq;
+
+use crate::prelude::*;
+
+const MINIMAP_SIZE: f32 = 220.0; // Pixels, square, in the bottom right corner
+const MINIMAP_MARGIN: f32 = 16.0;
+const MINIMAP_VIEW_SCREENS: f32 = 6.0; // How many screens across the minimap covers, so zooming out widens it too
+const MINIMAP_BACKGROUND: mq::Color = mq::Color::new(0.0, 0.0, 0.0, 0.6);
+const ARROW_SIZE: f32 = 14.0;
+const ARROW_MARGIN: f32 = 24.0; // From the edge of the screen
+
+/// A radar centred on the player showing bases, home and nearby enemies.
+/// Bases and home stay pinned to the edge when they're out of range so there's always a way to find them.
+pub fn draw_minimap(game: &Game) {
+    let corner = mq::vec2(mq::screen_width(), mq::screen_height()) - MINIMAP_MARGIN - MINIMAP_SIZE;
+    let centre = corner + MINIMAP_SIZE / 2.0;
+    let view_radius = MINIMAP_VIEW_SCREENS * f32::max(mq::screen_width(), mq::screen_height()) / game.camera.zoom / 2.0;
+    let scale = MINIMAP_SIZE / 2.0 / view_radius;
+    let player_position = game.player.game_object.position;
+    // Offset from the centre of the minimap, and whether it had to be pulled in to the edge
+    let to_minimap = |position: mq::Vec2| -> (mq::Vec2, bool) {
+        let offset = (position - player_position) * scale;
+        let edge = MINIMAP_SIZE / 2.0 - 4.0;
+        let clamped = offset.clamp(mq::Vec2::splat(-edge), mq::Vec2::splat(edge));
+        (centre + clamped, clamped != offset)
+    };
+
+    mq::draw_rectangle(corner.x, corner.y, MINIMAP_SIZE, MINIMAP_SIZE, MINIMAP_BACKGROUND);
+    mq::draw_rectangle_lines(corner.x, corner.y, MINIMAP_SIZE, MINIMAP_SIZE, 2.0, mq::LIME);
+    // What's on screen right now
+    let screen_size = mq::vec2(mq::screen_width(), mq::screen_height()) / game.camera.zoom * scale;
+    if screen_size.x < MINIMAP_SIZE {
+        mq::draw_rectangle_lines(centre.x - screen_size.x / 2.0, centre.y - screen_size.y / 2.0, screen_size.x, screen_size.y, 1.0, mq::GRAY);
+    }
+
+    for enemy in &game.enemies {
+        let (position, is_clamped) = to_minimap(enemy.game_object.position);
+        if !is_clamped {
+            mq::draw_rectangle(position.x - 1.0, position.y - 1.0, 2.0, 2.0, get_color(&enemy.game_object));
+        }
+    }
+    let (home, _) = to_minimap(game.home_base.position);
+    mq::draw_circle(home.x, home.y, 5.0, get_color(&game.home_base));
+    for base in &game.bases {
+        let (position, _) = to_minimap(base.game_object.position);
+        if base.collected {
+            mq::draw_circle_lines(position.x, position.y, 4.0, 1.5, get_color(&base.game_object));
+        } else {
+            mq::draw_circle(position.x, position.y, 4.0, get_color(&base.game_object));
+        }
+    }
+    mq::draw_circle(centre.x, centre.y, 3.0, mq::WHITE);
+}
+
+/// Arrows around the edge of the screen pointing at home and the bases still to collect, when they're off screen
+pub fn draw_objective_arrows(game: &Game) {
+    draw_arrow_if_off_screen(game.home_base.position, get_color(&game.home_base), &game.camera);
+    for base in game.bases.iter().filter(|base| !base.collected) {
+        draw_arrow_if_off_screen(base.game_object.position, get_color(&base.game_object), &game.camera);
+    }
+}
+
+fn draw_arrow_if_off_screen(position: mq::Vec2, color: mq::Color, camera: &Camera) {
+    let screen_size = mq::vec2(mq::screen_width(), mq::screen_height());
+    let screen_position = (position - camera.position) * camera.zoom;
+    if screen_position.cmpge(mq::Vec2::ZERO).all() && screen_position.cmple(screen_size).all() {
+        return;
+    }
+    // Shrink the offset from the middle of the screen until it sits just inside the edge
+    let half_size = screen_size / 2.0 - ARROW_MARGIN;
+    let offset = screen_position - screen_size / 2.0;
+    let shrink = f32::max(offset.x.abs() / half_size.x, offset.y.abs() / half_size.y);
+    let tip = screen_size / 2.0 + offset / shrink;
+    let direction = offset.normalize();
+    let back = tip - direction * ARROW_SIZE;
+    let side = direction.perp() * ARROW_SIZE / 2.0;
+    mq::draw_triangle(tip, back + side, back - side, color);
+}
+
+/// The color of something's first shape, which is what it's mostly drawn in
+fn get_color(game_object: &GameObject) -> mq::Color {
+    game_object.draw_shapes.first().map_or(mq::WHITE, |shape| shape.color)
+}
@@ -7,11 +7,18 @@ pub use crate::ship::Ship;
 pub use crate::ship::controller::ShipController;
 pub use crate::game_object::GameObject;
 pub use crate::game_object::health_status::HealthStatus;
+pub use crate::game_object::health::Health;
 pub use crate::game_object::draw_shape::DrawShape;
 pub use crate::ship::enemy_ship_type::EnemyShipType;
+pub use crate::ship::archetype::EnemyArchetypes;
 pub use crate::spawn_regions::SpawnRegion;
-pub use crate::team::Team;
+pub use crate::team::{Team, TeamRules};
 pub use crate::direction::Direction;
 pub use crate::direction::RotationDirection;
 pub use crate::game::Game;
+pub use crate::world::WorldSnapshot;
+pub use crate::entity::EntityId;
 pub use crate::level::Level;
+pub use crate::input::PlayerInput;
+pub use crate::rng::SeededRng;
+pub use crate::upgrades::{UpgradeKind, Upgrades};
@@ -0,0 +1,235 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use macroquad::prelude as mq;
+
+use crate::prelude::*;
+
+use crate::input::InputSource;
+
+const HEADER: &str = "space-game-replay 3";
+// Recorded before replays noted which version of their level they were played in
+const HEADER_V2: &str = "space-game-replay 2";
+// Recorded before the shop, when the player's ship was set by a single level
+const HEADER_V1: &str = "space-game-replay 1";
+
+/// Everything needed to replay a session: the world it was played in and the player's input on every tick.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub seed: u64,
+    pub upgrades: Upgrades,
+    pub bases_to_spend: u32,
+    pub level_path: Option<String>, // `None` for the built-in level
+    pub level_hash: Option<u64>, // The level's content hash when recorded; `None` in replays from before it was kept
+    pub inputs: Vec<PlayerInput>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{}", error),
+            ReplayError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl Recording {
+    pub fn new(seed: u64, upgrades: Upgrades, bases_to_spend: u32, level_path: Option<String>, level_hash: u64) -> Self {
+        Recording { seed, upgrades, bases_to_spend, level_path, level_hash: Some(level_hash), inputs: vec![] }
+    }
+
+    /// One line per tick: a bitmask of pressed buttons, the mouse offset, and the index of the upgrade being bought if any
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let upgrades = ron::to_string(&self.upgrades).expect("upgrades are always serializable");
+        let mut text = format!("{}\nseed {}\nupgrades {}\nbases_to_spend {}\n", HEADER, self.seed, upgrades, self.bases_to_spend);
+        if let Some(level_path) = &self.level_path {
+            text += &format!("level {}\n", level_path);
+        }
+        if let Some(level_hash) = self.level_hash {
+            text += &format!("level_hash {:016x}\n", level_hash);
+        }
+        for input in &self.inputs {
+            text += &format!("{} {} {}", encode_buttons(input), input.mouse_offset.x, input.mouse_offset.y);
+            if let Some(kind) = input.buy_upgrade {
+                text += &format!(" {}", kind.get_index());
+            }
+            text += "\n";
+        }
+        fs::write(path, text)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path)?;
+        Recording::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+        let parse_error = |line: usize, message: &str| ReplayError::Parse { line, message: message.to_string() };
+
+        let (line, header) = lines.next().ok_or(parse_error(1, "empty file"))?;
+        if header != HEADER && header != HEADER_V2 && header != HEADER_V1 {
+            return Err(parse_error(line, "not a replay file or unsupported version"));
+        }
+        let (line, seed) = lines.next().ok_or(parse_error(2, "missing seed"))?;
+        let seed = seed.strip_prefix("seed ").and_then(|seed| seed.parse().ok())
+            .ok_or(parse_error(line, "expected `seed <integer>`"))?;
+        let (upgrades, bases_to_spend) = if header == HEADER_V1 {
+            let (line, player_level) = lines.next().ok_or(parse_error(3, "missing player level"))?;
+            let player_level: f32 = player_level.strip_prefix("player_level ").and_then(|level| level.parse().ok())
+                .ok_or(parse_error(line, "expected `player_level <number>`"))?;
+            (Upgrades::from_player_level(player_level as u32), 0)
+        } else {
+            let (line, upgrades) = lines.next().ok_or(parse_error(3, "missing upgrades"))?;
+            let upgrades = upgrades.strip_prefix("upgrades ").and_then(|upgrades| ron::from_str(upgrades).ok())
+                .ok_or(parse_error(line, "expected `upgrades <upgrade levels>`"))?;
+            let (line, bases_to_spend) = lines.next().ok_or(parse_error(4, "missing bases to spend"))?;
+            let bases_to_spend = bases_to_spend.strip_prefix("bases_to_spend ").and_then(|bases| bases.parse().ok())
+                .ok_or(parse_error(line, "expected `bases_to_spend <integer>`"))?;
+            (upgrades, bases_to_spend)
+        };
+
+        let mut lines = lines.peekable();
+        let mut level_path = None;
+        if let Some((_, text)) = lines.peek() {
+            if let Some(path) = text.strip_prefix("level ") {
+                level_path = Some(path.to_string());
+                lines.next();
+            }
+        }
+        let mut level_hash = None;
+        if let Some(&(line, text)) = lines.peek() {
+            if let Some(hash) = text.strip_prefix("level_hash ") {
+                level_hash = Some(u64::from_str_radix(hash, 16).map_err(|_| parse_error(line, "expected `level_hash <hex>`"))?);
+                lines.next();
+            }
+        }
+
+        let mut inputs = vec![];
+        for (line, text) in lines {
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            let (buttons, x, y, upgrade) = match fields[..] {
+                [buttons, x, y] => (buttons, x, y, None),
+                [buttons, x, y, upgrade] => (buttons, x, y, Some(upgrade)),
+                _ => return Err(parse_error(line, "expected `<buttons> <mouse x> <mouse y> [upgrade]`")),
+            };
+            let buttons = buttons.parse().map_err(|_| parse_error(line, "invalid button mask"))?;
+            let x = x.parse().map_err(|_| parse_error(line, "invalid mouse x"))?;
+            let y = y.parse().map_err(|_| parse_error(line, "invalid mouse y"))?;
+            let mut input = decode_buttons(buttons, mq::vec2(x, y));
+            if let Some(upgrade) = upgrade {
+                let kind = upgrade.parse::<usize>().ok().and_then(|index| UpgradeKind::ALL.get(index))
+                    .ok_or(parse_error(line, "invalid upgrade"))?;
+                input.buy_upgrade = Some(*kind);
+            }
+            inputs.push(input);
+        }
+        Ok(Recording { seed, upgrades, bases_to_spend, level_path, level_hash, inputs })
+    }
+}
+
+fn encode_buttons(input: &PlayerInput) -> u8 {
+    [input.thrust_key, input.left_key, input.right_key, input.fire_key, input.restart_key, input.left_mouse, input.right_mouse]
+        .iter()
+        .enumerate()
+        .map(|(bit, &pressed)| (pressed as u8) << bit)
+        .sum()
+}
+
+fn decode_buttons(buttons: u8, mouse_offset: mq::Vec2) -> PlayerInput {
+    let pressed = |bit: u8| buttons & (1 << bit) != 0;
+    PlayerInput {
+        thrust_key: pressed(0),
+        left_key: pressed(1),
+        right_key: pressed(2),
+        fire_key: pressed(3),
+        restart_key: pressed(4),
+        left_mouse: pressed(5),
+        right_mouse: pressed(6),
+        mouse_offset,
+        buy_upgrade: None,
+    }
+}
+
+/// Feeds a recording back tick by tick, then goes idle once it runs out.
+pub struct ReplayInputSource {
+    inputs: Vec<PlayerInput>,
+    next_index: usize,
+}
+
+impl ReplayInputSource {
+    pub fn new(recording: Recording) -> Self {
+        ReplayInputSource { inputs: recording.inputs, next_index: 0 }
+    }
+}
+
+impl InputSource for ReplayInputSource {
+    fn next_input(&mut self, _game: &Game) -> PlayerInput {
+        let input = self.inputs.get(self.next_index).copied().unwrap_or_default();
+        self.next_index += 1;
+        input
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_index >= self.inputs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude as mq;
+
+    use super::*;
+    use crate::game::TICK_DURATION;
+    use crate::simulation::tests::new_level_1_simulation;
+
+    /// Thrusts, turns and fires in a pattern that changes every few ticks, with the mouse off to one side
+    fn scripted_input(game: &Game) -> PlayerInput {
+        let phase = game.ticks / 40;
+        PlayerInput {
+            thrust_key: phase.is_multiple_of(2),
+            left_key: phase % 3 == 1,
+            right_key: phase % 5 == 2,
+            fire_key: phase % 2 == 1,
+            right_mouse: phase % 4 == 3,
+            mouse_offset: mq::vec2(120.5, -33.25),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replay_matches_the_recorded_run() {
+        let mut original = new_level_1_simulation(7, Box::new(scripted_input));
+        original.start_recording(None, 0x0123456789abcdef);
+        original.run(600, TICK_DURATION);
+        let recording = original.recording.take().expect("recording was started");
+
+        let path = std::env::temp_dir().join(format!("space-game-test-{}.replay", std::process::id()));
+        recording.save(&path).expect("temp dir is writable");
+        let loaded = Recording::load(&path).expect("just saved");
+        fs::remove_file(&path).ok();
+        assert_eq!(loaded.inputs, recording.inputs);
+        assert_eq!(loaded.level_hash, Some(0x0123456789abcdef));
+
+        let mut replay = new_level_1_simulation(loaded.seed, Box::new(ReplayInputSource::new(loaded)));
+        replay.run(u64::MAX, TICK_DURATION);
+        assert_eq!(replay.tick, original.tick);
+        assert_eq!(replay.game.player.game_object.position, original.game.player.game_object.position);
+        assert_eq!(replay.game.enemies.len(), original.game.enemies.len());
+        assert_eq!(replay.game.bullets.len(), original.game.bullets.len());
+    }
+}
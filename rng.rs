@@ -0,0 +1,33 @@
+/// A small seedable PCG generator, the same algorithm as `mq::rand`.
+/// Unlike the global `mq::rand` state, each `Game` owns its own, so a seed always rebuilds the same world.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SeededRng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    /// Returns a value in `[low, high)`.
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        let unit = self.next_u32() as f64 / (u32::MAX as f64 + 1.0);
+        low + (high - low) * unit as f32
+    }
+}
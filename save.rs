@@ -0,0 +1,215 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::campaign::CampaignProgress;
+use crate::upgrades::Upgrades;
+
+/// Bump this whenever `SaveData` changes shape, and add a migration from the previous version to `SaveData::parse`.
+pub const SAVE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub progress: CampaignProgress,
+    pub settings: Settings,
+    pub upgrades: Upgrades,
+}
+
+/// Before the shop. Its player level came from the bases banked, which now all become bases to spend.
+#[derive(Deserialize)]
+struct SaveDataV1 {
+    progress: CampaignProgress,
+    settings: Settings,
+}
+
+impl From<SaveDataV1> for SaveData {
+    fn from(save: SaveDataV1) -> Self {
+        SaveData { version: SAVE_VERSION, progress: save.progress, settings: save.settings, upgrades: Upgrades::default() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub zoom: f32,
+    #[serde(default = "default_show_minimap")]
+    pub show_minimap: bool,
+}
+
+fn default_show_minimap() -> bool {
+    true
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        SaveData { version: SAVE_VERSION, progress: CampaignProgress::default(), settings: Settings::default(), upgrades: Upgrades::default() }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { zoom: 1.0, show_minimap: true }
+    }
+}
+
+/// Just enough of any save to know which version it is, before parsing the rest
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Corrupt(String),
+    NewerVersion(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{}", error),
+            SaveError::Corrupt(message) => write!(f, "corrupt save: {}", message),
+            SaveError::NewerVersion(version) => write!(f, "save is from a newer version of the game (format {}, this game reads up to {})", version, SAVE_VERSION),
+        }
+    }
+}
+
+/// A save that couldn't be loaded, and what was done with the file
+#[derive(Debug)]
+pub struct LoadFailure {
+    pub error: SaveError,
+    pub moved_to: Option<PathBuf>, // `None` if it was left where it was, in which case it mustn't be saved over
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl SaveData {
+    pub fn parse(text: &str) -> Result<Self, SaveError> {
+        let corrupt = |error: ron::error::SpannedError| SaveError::Corrupt(error.to_string());
+        let header: SaveHeader = ron::from_str(text).map_err(corrupt)?;
+        match header.version {
+            SAVE_VERSION => ron::from_str(text).map_err(corrupt),
+            1 => ron::from_str::<SaveDataV1>(text).map(SaveData::from).map_err(corrupt),
+            version if version > SAVE_VERSION => Err(SaveError::NewerVersion(version)),
+            version => Err(SaveError::Corrupt(format!("unknown format {}", version))),
+        }
+    }
+
+    /// Banked bases not yet spent in the shop
+    pub fn get_bases_to_spend(&self) -> u32 {
+        self.progress.get_bases_earned().saturating_sub(self.upgrades.get_total_cost())
+    }
+
+    pub fn to_text(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).expect("save data is always serializable")
+    }
+
+    /// Writes to a temporary file first, so a crash mid-save can't leave a half-written save behind
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let temporary_path = path.with_extension("tmp");
+        fs::write(&temporary_path, self.to_text())?;
+        fs::rename(&temporary_path, path)
+    }
+
+    /// Loads the save at `path`, starting fresh if there isn't one.
+    /// A corrupt save is moved aside to a new backup file. One that couldn't be read at all, or is from a newer version
+    /// of the game, is left alone, since it may well be fine; the failure then says not to save over it.
+    pub fn load_or_new(path: impl AsRef<Path>) -> (Self, Option<LoadFailure>) {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return (SaveData::default(), None),
+            Err(error) => return (SaveData::default(), Some(LoadFailure { error: error.into(), moved_to: None })),
+        };
+        match SaveData::parse(&text) {
+            Ok(save) => (save, None),
+            Err(error @ SaveError::Corrupt(_)) => {
+                let backup_path = get_backup_path(path);
+                let moved_to = match fs::rename(path, &backup_path) {
+                    Ok(()) => Some(backup_path),
+                    Err(backup_error) => {
+                        eprintln!("Could not move unreadable save to {}: {}", backup_path.display(), backup_error);
+                        None
+                    }
+                };
+                (SaveData::default(), Some(LoadFailure { error, moved_to }))
+            }
+            Err(error) => (SaveData::default(), Some(LoadFailure { error, moved_to: None })),
+        }
+    }
+}
+
+/// `<path>.bad`, or `<path>.bad.2`, `<path>.bad.3` and so on if earlier backups are still around
+fn get_backup_path(path: &Path) -> PathBuf {
+    (1..).map(|count| {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(if count == 1 { ".bad".to_string() } else { format!(".bad.{}", count) });
+        PathBuf::from(backup_path)
+    })
+    .find(|backup_path| !backup_path.exists())
+    .expect("some backup number is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for each test, so they can run in parallel
+    fn new_test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("space-game-save-test-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&directory).ok();
+        fs::create_dir_all(&directory).expect("temp dir is writable");
+        directory
+    }
+
+    #[test]
+    fn corrupt_saves_are_moved_to_separate_backups() {
+        let directory = new_test_directory("corrupt");
+        let path = directory.join("save.ron");
+        for attempt in ["first", "second"] {
+            fs::write(&path, attempt).unwrap();
+            let (_, failure) = SaveData::load_or_new(&path);
+            let failure = failure.expect("not a save");
+            assert!(matches!(failure.error, SaveError::Corrupt(_)));
+            assert_eq!(fs::read_to_string(failure.moved_to.expect("moved aside")).unwrap(), attempt);
+            assert!(!path.exists());
+        }
+        assert!(directory.join("save.ron.bad").exists() && directory.join("save.ron.bad.2").exists());
+        fs::remove_dir_all(&directory).ok();
+    }
+
+    #[test]
+    fn saves_from_newer_versions_are_left_alone() {
+        let directory = new_test_directory("newer");
+        let path = directory.join("save.ron");
+        let text = format!("(version: {}, something_new: true)", SAVE_VERSION + 1);
+        fs::write(&path, &text).unwrap();
+        let (_, failure) = SaveData::load_or_new(&path);
+        let failure = failure.expect("too new to read");
+        assert!(matches!(failure.error, SaveError::NewerVersion(_)));
+        assert!(failure.moved_to.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        fs::remove_dir_all(&directory).ok();
+    }
+
+    #[test]
+    fn unreadable_saves_are_left_alone() {
+        let directory = new_test_directory("unreadable");
+        let path = directory.join("save.ron");
+        fs::create_dir(&path).unwrap(); // Reading a directory fails, but not with `NotFound`
+        let (_, failure) = SaveData::load_or_new(&path);
+        let failure = failure.expect("can't read a directory");
+        assert!(matches!(failure.error, SaveError::Io(_)));
+        assert!(failure.moved_to.is_none() && path.is_dir());
+        fs::remove_dir_all(&directory).ok();
+    }
+}
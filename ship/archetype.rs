@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+use crate::game_object::health::ShieldStats;
+use crate::weapon::pattern::{FiringPattern, Mount};
+use crate::explosion::ExplosionStats;
+use crate::ship::targeting::TargetingMode;
+
+/// Everything that makes one kind of enemy ship different from another.
+#[derive(Debug, Clone)]
+pub struct EnemyArchetype {
+    pub radius: f32,
+    pub friction_multiplier: f32,
+    pub friction_constant: f32,
+    pub draw_shapes: Vec<DrawShape>,
+    pub hit_points: f32,
+    pub shield: Option<ShieldStats>,
+    pub thrust: f32,
+    pub rotate_speed: f32,
+    pub target_notice_distance: f32, // How far away it notices hostile ships, which are other factions' as well as the player
+    pub targeting: TargetingMode,
+    pub direction_offset_scale: f32, // How much of the base's per-ship direction offset this type uses
+    pub separation_distance: f32,    // How far this type tries to keep from other enemies; 0 doesn't try
+    pub weapon: Option<EnemyWeaponStats>,
+    pub death_explosion: Option<ExplosionStats>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyWeaponStats {
+    pub reload_time: f32,
+    pub bullet_lifetime: f32,
+    pub bullet_speed: f32,
+    #[serde(default = "default_damage")]
+    pub damage: f32,
+    #[serde(default)]
+    pub aimed: bool, // Only fire when lined up with its target, rather than constantly
+    #[serde(default)]
+    pub pattern: FiringPattern,
+    #[serde(default)]
+    pub mount: Mount,
+    #[serde(default)]
+    pub explosion: Option<ExplosionStats>,
+}
+
+/// All enemy archetypes by name. The ones in `data/enemy_archetypes.ron` as it was when the game was built are always present,
+/// and the file on disk can override them or add new ones.
+#[derive(Debug, Clone)]
+pub struct EnemyArchetypes {
+    archetypes: HashMap<String, EnemyArchetype>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArchetypeData {
+    radius: f32,
+    friction_multiplier: f32,
+    friction_constant: f32,
+    shapes: Vec<ShapeData>,
+    #[serde(default = "default_hit_points")]
+    hit_points: f32,
+    #[serde(default)]
+    shield: Option<ShieldStats>,
+    thrust: f32,
+    rotate_speed: f32,
+    #[serde(alias = "player_persue_distance")] // Its name from when the player was the only target
+    target_notice_distance: f32,
+    #[serde(default)]
+    targeting: TargetingMode,
+    #[serde(default)]
+    direction_offset_scale: f32,
+    #[serde(default)]
+    separation_distance: f32,
+    #[serde(default)]
+    weapon: Option<EnemyWeaponStats>,
+    #[serde(default)]
+    death_explosion: Option<ExplosionStats>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShapeData {
+    shape: ShapeKind,
+    color: String,
+    #[serde(default = "default_radius_scale")]
+    radius_scale: f32,
+}
+
+#[derive(Deserialize)]
+enum ShapeKind {
+    Circle,
+    Polygon(u8),
+    Line,
+}
+
+fn default_radius_scale() -> f32 {
+    1.0
+}
+
+fn default_hit_points() -> f32 {
+    1.0
+}
+
+fn default_damage() -> f32 {
+    1.0
+}
+
+#[derive(Debug)]
+pub enum ArchetypeFileError {
+    Io(io::Error),
+    Syntax(ron::error::SpannedError),
+    InvalidValue { archetype: String, message: String },
+}
+
+impl fmt::Display for ArchetypeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArchetypeFileError::Io(error) => write!(f, "{}", error),
+            ArchetypeFileError::Syntax(error) => write!(f, "{}", error),
+            ArchetypeFileError::InvalidValue { archetype, message } => write!(f, "archetype `{}`: {}", archetype, message),
+        }
+    }
+}
+
+impl From<io::Error> for ArchetypeFileError {
+    fn from(error: io::Error) -> Self {
+        ArchetypeFileError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ArchetypeFileError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ArchetypeFileError::Syntax(error)
+    }
+}
+
+// Compiled in so there are always archetypes, even if the data file is missing or broken
+const BUILT_IN_ARCHETYPES: &str = include_str!("../data/enemy_archetypes.ron");
+
+impl Default for EnemyArchetypes {
+    fn default() -> Self {
+        let mut archetypes = EnemyArchetypes { archetypes: HashMap::new() };
+        archetypes.add(BUILT_IN_ARCHETYPES).expect("the built-in archetype file is valid");
+        archetypes
+    }
+}
+
+impl EnemyArchetypes {
+    pub fn get(&self, enemy_type: &EnemyShipType) -> Option<&EnemyArchetype> {
+        self.archetypes.get(enemy_type.name())
+    }
+
+    pub fn contains(&self, enemy_type: &EnemyShipType) -> bool {
+        self.archetypes.contains_key(enemy_type.name())
+    }
+
+    pub fn get_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.archetypes.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// The built-in archetypes, overridden and extended by the file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ArchetypeFileError> {
+        let text = fs::read_to_string(path)?;
+        EnemyArchetypes::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ArchetypeFileError> {
+        let mut archetypes = EnemyArchetypes::default();
+        archetypes.add(text)?;
+        Ok(archetypes)
+    }
+
+    /// Adds every archetype in `text`, replacing any with the same name
+    fn add(&mut self, text: &str) -> Result<(), ArchetypeFileError> {
+        let data: HashMap<String, ArchetypeData> = ron::from_str(text)?;
+        for (name, archetype) in data {
+            let archetype = EnemyArchetype::from_data(&name, archetype)?;
+            self.archetypes.insert(name, archetype);
+        }
+        Ok(())
+    }
+}
+
+impl EnemyArchetype {
+    fn from_data(name: &str, data: ArchetypeData) -> Result<Self, ArchetypeFileError> {
+        let invalid = |message: String| ArchetypeFileError::InvalidValue { archetype: name.to_string(), message };
+        let numbers = [
+            ("radius", data.radius), ("friction_multiplier", data.friction_multiplier), ("friction_constant", data.friction_constant),
+            ("thrust", data.thrust), ("rotate_speed", data.rotate_speed), ("target_notice_distance", data.target_notice_distance),
+            ("separation_distance", data.separation_distance),
+        ];
+        for (field, value) in numbers {
+            if !value.is_finite() || value < 0.0 {
+                return Err(invalid(format!("{} must be a non-negative number, got {}", field, value)));
+            }
+        }
+        if data.radius == 0.0 {
+            return Err(invalid(format!("radius must be greater than 0")));
+        }
+        if !(data.hit_points > 0.0 && data.hit_points.is_finite()) {
+            return Err(invalid(format!("hit_points must be greater than 0, got {}", data.hit_points)));
+        }
+        if let Some(shield) = &data.shield {
+            if !(shield.max_shield >= 0.0 && shield.regen_rate >= 0.0 && shield.regen_delay >= 0.0) {
+                return Err(invalid(format!("shield max_shield, regen_rate and regen_delay must not be negative")));
+            }
+        }
+        if let Some(weapon) = &data.weapon {
+            if !(weapon.reload_time > 0.0 && weapon.bullet_lifetime > 0.0 && weapon.bullet_speed > 0.0) {
+                return Err(invalid(format!("weapon reload_time, bullet_lifetime and bullet_speed must be greater than 0")));
+            }
+            if !(weapon.damage >= 0.0 && weapon.damage.is_finite()) {
+                return Err(invalid(format!("weapon damage must not be negative, got {}", weapon.damage)));
+            }
+            let is_positive = |value: f32| value > 0.0 && value.is_finite();
+            let is_pattern_valid = match weapon.pattern {
+                FiringPattern::Single | FiringPattern::Mine => true,
+                FiringPattern::Spread { count, angle } => count > 0 && angle >= 0.0 && angle.is_finite(),
+                FiringPattern::Burst { count, interval } => count > 0 && is_positive(interval),
+                FiringPattern::Homing { turn_rate, seek_range } => turn_rate >= 0.0 && turn_rate.is_finite() && is_positive(seek_range),
+                FiringPattern::Beam { length } => is_positive(length),
+            };
+            if !is_pattern_valid {
+                return Err(invalid(format!("weapon pattern {:?} needs counts of at least 1, lengths, ranges and intervals above 0, and angles and turn rates not below 0", weapon.pattern)));
+            }
+        }
+        let explosions = data.weapon.iter().filter_map(|weapon| weapon.explosion).chain(data.death_explosion);
+        for explosion in explosions {
+            if !(explosion.radius > 0.0 && explosion.radius.is_finite()) {
+                return Err(invalid(format!("explosion radius must be greater than 0, got {}", explosion.radius)));
+            }
+            if !(explosion.damage >= 0.0 && explosion.damage.is_finite() && explosion.knockback.is_finite()) {
+                return Err(invalid(format!("explosion damage must not be negative and knockback must be a number")));
+            }
+        }
+        let mut draw_shapes = vec![];
+        for shape in data.shapes {
+            let color = parse_color(&shape.color).ok_or_else(|| invalid(format!("unknown color `{}`, expected a name like BLUE or #RRGGBB", shape.color)))?;
+            let mut draw_shape = match shape.shape {
+                ShapeKind::Circle => DrawShape::new_circle_color(color),
+                ShapeKind::Polygon(sides) => DrawShape::new_polygon_color(sides, color),
+                ShapeKind::Line => DrawShape::new_line_color(color),
+            };
+            draw_shape.radius_scale = shape.radius_scale;
+            draw_shapes.push(draw_shape);
+        }
+        Ok(EnemyArchetype {
+            radius: data.radius,
+            friction_multiplier: data.friction_multiplier,
+            friction_constant: data.friction_constant,
+            draw_shapes,
+            hit_points: data.hit_points,
+            shield: data.shield,
+            thrust: data.thrust,
+            rotate_speed: data.rotate_speed,
+            target_notice_distance: data.target_notice_distance,
+            targeting: data.targeting,
+            direction_offset_scale: data.direction_offset_scale,
+            separation_distance: data.separation_distance,
+            weapon: data.weapon,
+            death_explosion: data.death_explosion,
+        })
+    }
+}
+
+/// A macroquad color name such as `DARKBLUE`, or a hex color `#RRGGBB` / `#RRGGBBAA`
+fn parse_color(text: &str) -> Option<mq::Color> {
+    if let Some(hex) = text.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        return match hex.len() {
+            6 => Some(mq::Color::from_hex(value)),
+            8 => Some(mq::Color::from_rgba((value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8)),
+            _ => None,
+        };
+    }
+    let color = match text {
+        "LIGHTGRAY" => mq::LIGHTGRAY, "GRAY" => mq::GRAY, "DARKGRAY" => mq::DARKGRAY,
+        "YELLOW" => mq::YELLOW, "GOLD" => mq::GOLD, "ORANGE" => mq::ORANGE,
+        "PINK" => mq::PINK, "RED" => mq::RED, "MAROON" => mq::MAROON,
+        "GREEN" => mq::GREEN, "LIME" => mq::LIME, "DARKGREEN" => mq::DARKGREEN,
+        "SKYBLUE" => mq::SKYBLUE, "BLUE" => mq::BLUE, "DARKBLUE" => mq::DARKBLUE,
+        "PURPLE" => mq::PURPLE, "VIOLET" => mq::VIOLET, "DARKPURPLE" => mq::DARKPURPLE,
+        "BEIGE" => mq::BEIGE, "BROWN" => mq::BROWN, "DARKBROWN" => mq::DARKBROWN,
+        "WHITE" => mq::WHITE, "BLACK" => mq::BLACK, "MAGENTA" => mq::MAGENTA,
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_archetypes_cover_every_built_in_type() {
+        let archetypes = EnemyArchetypes::default();
+        for enemy_type in EnemyShipType::BUILT_IN {
+            assert!(archetypes.contains(&enemy_type), "no archetype for {}", enemy_type.name());
+        }
+    }
+
+    #[test]
+    fn file_overrides_built_in_archetypes_and_keeps_the_rest() {
+        let text = r#"{"Slow": (radius: 5.0, friction_multiplier: 0.5, friction_constant: 1.0, shapes: [], thrust: 1.0, rotate_speed: 1.0, target_notice_distance: 1.0)}"#;
+        let archetypes = EnemyArchetypes::parse(text).expect("valid archetype");
+        assert_eq!(archetypes.get(&EnemyShipType::Slow).map(|archetype| archetype.radius), Some(5.0));
+        assert!(archetypes.contains(&EnemyShipType::Sniper));
+    }
+
+    #[test]
+    fn archetypes_using_the_old_notice_distance_name_still_load() {
+        let text = r#"{"Slow": (radius: 5.0, friction_multiplier: 0.5, friction_constant: 1.0, shapes: [], thrust: 1.0, rotate_speed: 1.0, player_persue_distance: 700.0)}"#;
+        let archetypes = EnemyArchetypes::parse(text).expect("valid archetype");
+        assert_eq!(archetypes.get(&EnemyShipType::Slow).map(|archetype| archetype.target_notice_distance), Some(700.0));
+    }
+
+    #[test]
+    fn broken_firing_patterns_are_rejected() {
+        for pattern in ["Spread(count: 0, angle: 0.5)", "Burst(count: 3, interval: 0.0)", "Beam(length: -1.0)", "Homing(turn_rate: 2.0, seek_range: inf)"] {
+            let text = format!(r#"{{"Broken": (radius: 5.0, friction_multiplier: 0.5, friction_constant: 1.0, shapes: [], thrust: 1.0, rotate_speed: 1.0, target_notice_distance: 1.0,
+                weapon: Some((reload_time: 1.0, bullet_lifetime: 1.0, bullet_speed: 1.0, pattern: {}))) }}"#, pattern);
+            let error = EnemyArchetypes::parse(&text).err().unwrap_or_else(|| panic!("{} should be rejected", pattern));
+            assert!(matches!(error, ArchetypeFileError::InvalidValue { .. }), "{}: {}", pattern, error);
+        }
+    }
+}
@@ -4,11 +4,21 @@ use crate::prelude::*;
 use macroquad::prelude as mq;
 use dyn_clone::DynClone;
 
-use crate::keybinds;
+use super::targeting::{Target, TargetSelector, TargetingMode};
+use crate::world::BulletSnapshot;
+use crate::base::GuardOrders;
+
+const SCATTER_LOOKAHEAD: f32 = 100.0; // How far ahead a scattering guard aims, only its direction matters
 
 pub trait ShipController: DynClone {
-    fn is_thrusting (&self, ship: &Ship, game: &Game) -> bool;
-    fn get_rotation(&self, ship: &Ship, game: &Game) -> RotationDirection;
+    fn is_thrusting (&self, ship: &Ship, world: &WorldSnapshot) -> bool;
+    fn get_rotation(&self, ship: &Ship, world: &WorldSnapshot) -> RotationDirection;
+
+    /// Called on each of a base's guards when the player picks it up
+    fn on_base_collected(&mut self, _orders: GuardOrders) {}
+
+    /// Called on each of a base's guards when the player loses it and it goes back where it came from
+    fn on_base_returned(&mut self) {}
 }
 
 dyn_clone::clone_trait_object!(ShipController);
@@ -17,11 +27,11 @@ dyn_clone::clone_trait_object!(ShipController);
 pub struct EmptyShipController;
 
 impl ShipController for EmptyShipController {
-    fn is_thrusting (&self, _ship: &Ship, _game: &Game) -> bool {
+    fn is_thrusting (&self, _ship: &Ship, _world: &WorldSnapshot) -> bool {
         false
     }
 
-    fn get_rotation(&self, _ship: &Ship, _game: &Game) -> RotationDirection {
+    fn get_rotation(&self, _ship: &Ship, _world: &WorldSnapshot) -> RotationDirection {
         RotationDirection::None
     }
 }
@@ -30,16 +40,16 @@ impl ShipController for EmptyShipController {
 pub struct KeyboardShipController;
 
 impl ShipController for KeyboardShipController {
-    fn is_thrusting (&self, _object: &Ship, _game: &Game) -> bool {
-        mq::is_key_down(keybinds::THRUST_KEY)
+    fn is_thrusting (&self, _object: &Ship, world: &WorldSnapshot) -> bool {
+        world.input.thrust_key
     }
 
-    fn get_rotation(&self, _object: &Ship, _game: &Game) -> RotationDirection {
+    fn get_rotation(&self, _object: &Ship, world: &WorldSnapshot) -> RotationDirection {
         let mut rotate = 0;
-        if mq::is_key_down(keybinds::LEFT_KEY) {
+        if world.input.left_key {
             rotate -= 1;
         }
-        if mq::is_key_down(keybinds::RIGHT_KEY) {
+        if world.input.right_key {
             rotate += 1;
         }
         RotationDirection::from_i32(rotate)
@@ -50,46 +60,91 @@ impl ShipController for KeyboardShipController {
 pub struct MouseShipController;
 
 impl ShipController for MouseShipController {
-    fn is_thrusting (&self, _object: &Ship, _game: &Game) -> bool {
-        mq::is_mouse_button_down(mq::MouseButton::Left)
+    fn is_thrusting (&self, _object: &Ship, world: &WorldSnapshot) -> bool {
+        world.input.left_mouse
     }
 
-    fn get_rotation(&self, ship: &Ship, _game: &Game) -> RotationDirection {
-        let middle = mq::vec2(mq::screen_width(), mq::screen_height()) / 2.0;
-        let mouse_pos = mq::vec2(mq::mouse_position().0, mq::mouse_position().1);
-        let mouse_from_mid = mouse_pos - middle;
-        let target_direction = Direction::new_from_vec(mouse_from_mid);
+    fn get_rotation(&self, ship: &Ship, world: &WorldSnapshot) -> RotationDirection {
+        let target_direction = Direction::new_from_vec(world.input.mouse_offset);
         Direction::get_shorter_rotation_direction(ship.game_object.direction, target_direction, PI / 180.0)
     }
 }
 
 #[derive(Clone)]
 pub struct EnemyShipController {
-    base: Option<Base>,
-    player_persue_distance: f32,
+    base: Option<EntityId>, // Looked up each tick, so it sees the base move or get collected
+    orders: Option<GuardOrders>, // How it reacted to its base being collected, until the base is returned
+    selector: TargetSelector,
     player_direction_offset: f32,
+    separation_distance: f32, // Steers away from other enemies closer than this; 0 turns it off
     epsilon: f32,
 }
 
 impl EnemyShipController {
-    pub fn new(base: Option<Base>, player_persue_distance: f32, player_direction_offset: f32) -> Self {
+    pub fn new(base: Option<EntityId>, selector: TargetSelector, player_direction_offset: f32, separation_distance: f32) -> Self {
         EnemyShipController {
             base,
-            player_persue_distance,
+            selector,
             player_direction_offset,
+            separation_distance,
             ..Default::default()
         }
     }
-    fn get_target(&self, ship: &Ship, game: &Game) -> (Option<mq::Vec2>, Option<mq::Vec2>) {
+    /// Where the ship is heading, nudged away from crowding neighbours
+    fn get_target(&self, ship: &Ship, world: &WorldSnapshot) -> (Option<mq::Vec2>, Option<mq::Vec2>) {
+        let (target_pos, target_velocity) = self.get_goal(ship, world);
+        let separation = self.get_separation(ship, world) * self.separation_distance;
+        if separation == mq::Vec2::ZERO {
+            return (target_pos, target_velocity);
+        }
+        (Some(target_pos.unwrap_or(ship.game_object.position) + separation), target_velocity)
+    }
+    /// Points away from nearby enemies, more strongly the closer they are, so groups spread out instead of stacking
+    fn get_separation(&self, ship: &Ship, world: &WorldSnapshot) -> mq::Vec2 {
+        if self.separation_distance <= 0.0 {
+            return mq::Vec2::ZERO;
+        }
+        let position = ship.game_object.position;
+        let mut separation = mq::Vec2::ZERO;
+        for index in world.enemy_grid.query(position, self.separation_distance) {
+            let offset = position - world.enemies[index].position;
+            let distance = offset.length();
+            if distance == 0.0 || distance >= self.separation_distance {
+                continue; // Either too far, or this ship itself
+            }
+            separation += offset / distance * (1.0 - distance / self.separation_distance);
+        }
+        separation
+    }
+    fn get_goal(&self, ship: &Ship, world: &WorldSnapshot) -> (Option<mq::Vec2>, Option<mq::Vec2>) {
         let ship_pos = ship.game_object.position;
-        let player_pos = game.player.game_object.position;
-        let player_dist = mq::Vec2::distance(ship_pos, player_pos);
-        if let Some(base) = &self.base {
-            let base_pos = base.game_object.position;
+        match self.orders {
+            Some(GuardOrders::Chase { carrier }) => {
+                let is_carried = self.base.and_then(|id| world.get_base(id)).is_some_and(|base| base.collected);
+                if let (true, Some(carrier)) = (is_carried, world.get_ship(carrier)) {
+                    return (Some(carrier.position), Some(carrier.velocity));
+                }
+            }
+            Some(GuardOrders::Scatter { from, until }) if world.elapsed_time < until => {
+                let away = (ship_pos - from).normalize_or(ship.game_object.direction.get_as_vec());
+                return (Some(ship_pos + away * SCATTER_LOOKAHEAD), None);
+            }
+            _ => {}
+        }
+        let base_id = match self.orders {
+            Some(GuardOrders::Scatter { .. }) => None, // Roams once it's done fleeing
+            Some(GuardOrders::FallBack { base }) => base,
+            _ => self.base,
+        };
+        let base = base_id.and_then(|id| world.get_base(id));
+        let target = self.selector.select(ship, base, world);
+        if let Some(base) = base {
+            let base_pos = base.position;
             let base_dist = mq::Vec2::distance(ship_pos, base_pos);
-            let player_base_dist = mq::Vec2::distance(player_pos, base_pos);
-            if player_dist < self.player_persue_distance && player_base_dist < base.max_distance {
-                return (Some(player_pos), Some(game.player.game_object.velocity));
+            if let Some(target) = target {
+                if mq::Vec2::distance(target.position, base_pos) < base.max_distance {
+                    return (Some(target.position), Some(target.velocity));
+                }
             }
             if base_dist > base.optimal_distance + self.epsilon {
                 return (Some(base_pos), None);
@@ -100,8 +155,8 @@ impl EnemyShipController {
                 return (Some(base_pos + direction_vec_from_base * base.optimal_distance), None);
             }
         }
-        if player_dist < self.player_persue_distance {
-            return (Some(player_pos), Some(game.player.game_object.velocity));
+        if let Some(target) = target {
+            return (Some(target.position), Some(target.velocity));
         }
         (None, None)
     }
@@ -109,13 +164,13 @@ impl EnemyShipController {
 
 impl Default for EnemyShipController {
     fn default() -> Self {
-        EnemyShipController { base: None, player_persue_distance: 600.0, player_direction_offset: 0.0, epsilon: 1.0 }
+        EnemyShipController { base: None, orders: None, selector: TargetSelector::new(TargetingMode::Nearest, 600.0), player_direction_offset: 0.0, separation_distance: 0.0, epsilon: 1.0 }
     }
 }
 
 impl ShipController for EnemyShipController {
-    fn is_thrusting (&self, ship: &Ship, game: &Game) -> bool {
-        let (target_pos, _) = self.get_target(ship, game);
+    fn is_thrusting (&self, ship: &Ship, world: &WorldSnapshot) -> bool {
+        let (target_pos, _) = self.get_target(ship, world);
         if let Some(target) = target_pos {
             let dot = ship.game_object.direction.get_as_vec().dot(target - ship.game_object.position);
             return dot > 0.0;
@@ -123,8 +178,8 @@ impl ShipController for EnemyShipController {
         false
     }
 
-    fn get_rotation(&self, ship: &Ship, game: &Game) -> RotationDirection {
-        let (target_pos, opt_target_velocity) = self.get_target(ship, game);
+    fn get_rotation(&self, ship: &Ship, world: &WorldSnapshot) -> RotationDirection {
+        let (target_pos, opt_target_velocity) = self.get_target(ship, world);
         let prediction_speed;
         if let Some(weapon) = ship.weapons.first() {
             prediction_speed = weapon.bullet_speed;
@@ -149,85 +204,106 @@ impl ShipController for EnemyShipController {
         }
         RotationDirection::None
     }
+
+    fn on_base_collected(&mut self, orders: GuardOrders) {
+        self.orders = Some(orders);
+    }
+
+    fn on_base_returned(&mut self) {
+        self.orders = None; // Back to guarding it
+    }
 }
 
+/// Dodges bullets and its target's aim, and otherwise leads its target like a player would
 #[derive(Clone)]
-pub struct EnemyCloneShipController;
+pub struct EnemyCloneShipController {
+    selector: TargetSelector,
+}
 
 impl EnemyCloneShipController {
-    fn get_closest_bullet_threat(ship: &Ship, game: &Game) -> Option<Bullet> {
+    pub fn new(selector: TargetSelector) -> Self {
+        EnemyCloneShipController { selector }
+    }
+    fn get_closest_bullet_threat(ship: &Ship, world: &WorldSnapshot) -> Option<BulletSnapshot> {
         let mut closest_distance = f32::INFINITY;
         let mut closest_bullet = None;
-        for bullet in &game.bullets {
-            if bullet.team == ship.team {
+        for bullet in &world.bullets {
+            if !world.team_rules.is_hostile(bullet.team, ship.team) {
                 continue;
             }
-            let vec_from_bullet = ship.game_object.position - bullet.game_object.position;
+            let vec_from_bullet = ship.game_object.position - bullet.position;
             let direction = Direction::new_from_vec(vec_from_bullet);
-            let difference = direction - bullet.game_object.direction;
+            let difference = direction - bullet.direction;
             if difference.get().abs() < PI / 18.0 {
-                let distance = mq::Vec2::distance(ship.game_object.position, bullet.game_object.position);
+                let distance = mq::Vec2::distance(ship.game_object.position, bullet.position);
                 if distance < closest_distance {
                     closest_distance = distance;
                     closest_bullet = Some(bullet);
                 }
             }
         }
-        closest_bullet.cloned()
+        closest_bullet.copied()
     }
-    fn is_player_threat(ship: &Ship, game: &Game) -> bool {
-        let vec_from_player = ship.game_object.position - game.player.game_object.position;
-        let direction = Direction::new_from_vec(vec_from_player);
-        let difference = direction - game.player.game_object.direction;
+    fn is_target_threat(ship: &Ship, target: &Target) -> bool {
+        let vec_from_target = ship.game_object.position - target.position;
+        let direction = Direction::new_from_vec(vec_from_target);
+        let difference = direction - target.direction;
         difference.get().abs() < PI / 4.0
     }
 }
 
 impl ShipController for EnemyCloneShipController {
-    fn is_thrusting (&self, ship: &Ship, game: &Game) -> bool {
-        let closest_bullet = EnemyCloneShipController::get_closest_bullet_threat(ship, game);
-        let dot = ship.game_object.direction.get_as_vec().dot(game.player.game_object.position - ship.game_object.position);
-        closest_bullet.is_some() || dot > 0.0 || EnemyCloneShipController::is_player_threat(ship, game)
+    fn is_thrusting (&self, ship: &Ship, world: &WorldSnapshot) -> bool {
+        let closest_bullet = EnemyCloneShipController::get_closest_bullet_threat(ship, world);
+        let Some(target) = self.selector.select(ship, None, world) else {
+            return closest_bullet.is_some();
+        };
+        let dot = ship.game_object.direction.get_as_vec().dot(target.position - ship.game_object.position);
+        closest_bullet.is_some() || dot > 0.0 || EnemyCloneShipController::is_target_threat(ship, &target)
     }
 
-    fn get_rotation(&self, ship: &Ship, game: &Game) -> RotationDirection {
+    fn get_rotation(&self, ship: &Ship, world: &WorldSnapshot) -> RotationDirection {
         let weapon = ship.weapons.first().expect("pls");
-        let closest_bullet = EnemyCloneShipController::get_closest_bullet_threat(ship, game);
+        let closest_bullet = EnemyCloneShipController::get_closest_bullet_threat(ship, world);
+        let target = self.selector.select(ship, None, world);
         let target_direction;
         if let Some(closest_bullet) = closest_bullet {
-            let speed = closest_bullet.game_object.velocity.length();
-            let distance = mq::Vec2::distance(ship.game_object.position, closest_bullet.game_object.position);
+            let speed = closest_bullet.velocity.length();
+            let distance = mq::Vec2::distance(ship.game_object.position, closest_bullet.position);
             let time = distance / speed;
             let future_ship_pos = ship.game_object.position + 0.5 * ship.game_object.velocity * time;
-            let direction_from_bullet = Direction::new_from_vec(future_ship_pos - closest_bullet.game_object.position);
-            let bullet_miss_angle = direction_from_bullet - closest_bullet.game_object.direction;
+            let direction_from_bullet = Direction::new_from_vec(future_ship_pos - closest_bullet.position);
+            let bullet_miss_angle = direction_from_bullet - closest_bullet.direction;
             target_direction = if bullet_miss_angle.get() > 0.0 {
-                closest_bullet.game_object.direction + Direction::new(PI / 2.0)
+                closest_bullet.direction + Direction::new(PI / 2.0)
             } else {
-                closest_bullet.game_object.direction - Direction::new(PI / 2.0)
+                closest_bullet.direction - Direction::new(PI / 2.0)
             };
-        } else if EnemyCloneShipController::is_player_threat(ship, game) && weapon.time_until_reloaded > weapon.reload_time / 2.0 {
-            let direction_from_player = Direction::new_from_vec(ship.game_object.position - game.player.game_object.position);
-            let player_miss_angle = direction_from_player - game.player.game_object.direction;
-            target_direction = if player_miss_angle.get() > 0.0 {
-                game.player.game_object.direction + Direction::new(PI * 0.75)
+        } else if let Some(target) = target {
+            if EnemyCloneShipController::is_target_threat(ship, &target) && weapon.time_until_reloaded > weapon.reload_time / 2.0 {
+                let direction_from_target = Direction::new_from_vec(ship.game_object.position - target.position);
+                let target_miss_angle = direction_from_target - target.direction;
+                target_direction = if target_miss_angle.get() > 0.0 {
+                    target.direction + Direction::new(PI * 0.75)
+                } else {
+                    target.direction - Direction::new(PI * 0.75)
+                };
             } else {
-                game.player.game_object.direction - Direction::new(PI * 0.75)
-            };
-        } else {
-            let mut player_pos = game.player.game_object.position;
-            let mut lookahead_seconds = 0.0;
-            for _ in 0..3 {
-                let player_dist = mq::Vec2::distance(ship.game_object.position, player_pos);
-                let speed_towards_player = ship.game_object.velocity.dot((player_pos - ship.game_object.position).normalize());
-                let speed = weapon.bullet_speed + speed_towards_player;
-                lookahead_seconds = player_dist / speed;
-                player_pos = game.player.game_object.position + lookahead_seconds * game.player.game_object.velocity;
+                let mut target_pos = target.position;
+                let mut lookahead_seconds = 0.0;
+                for _ in 0..3 {
+                    let target_dist = mq::Vec2::distance(ship.game_object.position, target_pos);
+                    let speed_towards_target = ship.game_object.velocity.dot((target_pos - ship.game_object.position).normalize());
+                    let speed = weapon.bullet_speed + speed_towards_target;
+                    lookahead_seconds = target_dist / speed;
+                    target_pos = target.position + lookahead_seconds * target.velocity;
+                }
+                let future_self_pos = ship.game_object.position + lookahead_seconds * ship.game_object.velocity;
+                let offset = target_pos.sub(future_self_pos);
+                target_direction = Direction::new_from_vec(offset);
             }
-            let future_self_pos = ship.game_object.position + lookahead_seconds * ship.game_object.velocity;
-            let offset = player_pos.sub(future_self_pos);
-            target_direction = Direction::new_from_vec(offset);
-
+        } else {
+            return RotationDirection::None;
         }
         Direction::get_shorter_rotation_direction(ship.game_object.direction, target_direction, PI / 180.0)
     }
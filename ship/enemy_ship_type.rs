@@ -1,4 +1,4 @@
-#[derive(Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum EnemyShipType {
     UltraLowFriction,
     LowFriction,
@@ -9,4 +9,42 @@ pub enum EnemyShipType {
     Sniper,
     Glider,
     Clone,
+    Custom(String), // An archetype only defined in the archetype data file
+}
+
+impl EnemyShipType {
+    /// The built-in types, which always have an archetype even without a data file
+    pub const BUILT_IN: [EnemyShipType; 9] = [
+        EnemyShipType::UltraLowFriction,
+        EnemyShipType::LowFriction,
+        EnemyShipType::HighFriction,
+        EnemyShipType::Slow,
+        EnemyShipType::Shoot,
+        EnemyShipType::Turret,
+        EnemyShipType::Sniper,
+        EnemyShipType::Glider,
+        EnemyShipType::Clone,
+    ];
+
+    /// The archetype name used for this type in data files
+    pub fn name(&self) -> &str {
+        match self {
+            EnemyShipType::UltraLowFriction => "UltraLowFriction",
+            EnemyShipType::LowFriction => "LowFriction",
+            EnemyShipType::HighFriction => "HighFriction",
+            EnemyShipType::Slow => "Slow",
+            EnemyShipType::Shoot => "Shoot",
+            EnemyShipType::Turret => "Turret",
+            EnemyShipType::Sniper => "Sniper",
+            EnemyShipType::Glider => "Glider",
+            EnemyShipType::Clone => "Clone",
+            EnemyShipType::Custom(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        EnemyShipType::BUILT_IN.into_iter()
+            .find(|enemy_type| enemy_type.name() == name)
+            .unwrap_or_else(|| EnemyShipType::Custom(name.to_string()))
+    }
 }
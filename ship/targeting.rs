@@ -0,0 +1,88 @@
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+use crate::world::{BaseSnapshot, ShipSnapshot};
+
+/// How an AI ship picks which hostile ship to go after
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum TargetingMode {
+    #[default]
+    Nearest,
+    HighestThreat, // Close ships pointed its way first
+    LowestHealth,  // Finishes off whatever is weakest
+    ProtectBase,   // Whatever is closest to its base and inside `max_distance` of it; the same as `Nearest` without a base
+}
+
+/// What a controller knows about its target, copied from the world snapshot
+#[derive(Debug, Clone, Copy)]
+pub struct Target {
+    pub id: EntityId,
+    pub team: Team,
+    pub position: mq::Vec2,
+    pub velocity: mq::Vec2,
+    pub direction: Direction,
+    pub health: f32, // Hit points plus shield
+}
+
+impl Target {
+    fn new(target: &ShipSnapshot) -> Self {
+        Target { id: target.id, team: target.team, position: target.position, velocity: target.velocity, direction: target.direction, health: target.health }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TargetSelector {
+    pub mode: TargetingMode,
+    pub range: f32, // Ships further than this are ignored; may be infinite
+}
+
+impl TargetSelector {
+    pub fn new(mode: TargetingMode, range: f32) -> Self {
+        TargetSelector { mode, range }
+    }
+
+    /// The hostile ship `ship` should go after, if any are in range
+    pub fn select(&self, ship: &Ship, base: Option<&BaseSnapshot>, world: &WorldSnapshot) -> Option<Target> {
+        let position = ship.game_object.position;
+        if let (TargetingMode::ProtectBase, Some(base)) = (self.mode, base) {
+            let base_position = base.position;
+            return get_hostiles(ship.team, base_position, base.max_distance, world).into_iter()
+                .min_by(|x, y| x.position.distance_squared(base_position).total_cmp(&y.position.distance_squared(base_position)));
+        }
+        let candidates = get_hostiles(ship.team, position, self.range, world).into_iter();
+        let distance = |target: &Target| target.position.distance(position);
+        match self.mode {
+            TargetingMode::Nearest | TargetingMode::ProtectBase => candidates.min_by(|x, y| distance(x).total_cmp(&distance(y))),
+            TargetingMode::LowestHealth => candidates.min_by(|x, y| x.health.total_cmp(&y.health).then(distance(x).total_cmp(&distance(y)))),
+            TargetingMode::HighestThreat => {
+                // From 0 facing directly away to 2 facing straight at it, over how far away it is
+                let threat = |target: &Target| {
+                    let alignment = target.direction.get_as_vec().dot((position - target.position).normalize_or_zero());
+                    (1.0 + alignment) / distance(target).max(1.0)
+                };
+                candidates.max_by(|x, y| threat(x).total_cmp(&threat(y)))
+            }
+        }
+    }
+}
+
+/// Every ship `team` is hostile to that's nearer than `max_distance` to `position`.
+/// Enemies are only looked at when some of them are on different sides.
+fn get_hostiles(team: Team, position: mq::Vec2, max_distance: f32, world: &WorldSnapshot) -> Vec<Target> {
+    let mut candidates = vec![&world.player];
+    if world.enemies_fight_each_other {
+        let indices = if max_distance.is_finite() {
+            world.target_grid.query(position, max_distance)
+        } else {
+            (0..world.enemies.len()).collect()
+        };
+        candidates.extend(indices.into_iter().map(|index| &world.enemies[index]));
+    }
+    candidates.into_iter()
+        .filter(|candidate| world.team_rules.is_hostile(team, candidate.team))
+        .filter(|candidate| candidate.position.distance(position) < max_distance)
+        .map(Target::new)
+        .collect()
+}
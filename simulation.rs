@@ -0,0 +1,173 @@
+use crate::prelude::*;
+
+use crate::input::InputSource;
+use crate::game::TICK_DURATION;
+use crate::replay::Recording;
+
+// Longest frame that is simulated in full; anything longer is slowed down instead of freezing in catch-up
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Drives a `Game` tick by tick from an `InputSource`.
+/// Nothing in here touches macroquad's window, so it also runs headless.
+pub struct Simulation {
+    pub game: Game,
+    pub input_source: Box<dyn InputSource>,
+    pub tick: u64,
+    pub recording: Option<Recording>,
+    accumulator: f32,
+}
+
+impl Simulation {
+    pub fn new(game: Game, input_source: Box<dyn InputSource>) -> Self {
+        Simulation { game, input_source, tick: 0, recording: None, accumulator: 0.0 }
+    }
+
+    /// Records every input from now on, for saving as a replay
+    pub fn start_recording(&mut self, level_path: Option<String>, level_hash: u64) {
+        let game = &self.game;
+        self.recording = Some(Recording::new(game.seed, game.upgrades.clone(), game.bases_to_spend, level_path, level_hash));
+    }
+
+    pub fn step(&mut self, delta_t: f32) {
+        let input = self.input_source.next_input(&self.game);
+        if let Some(recording) = &mut self.recording {
+            recording.inputs.push(input);
+        }
+        self.game.update(delta_t, input);
+        self.tick += 1;
+    }
+
+    /// Runs as many fixed `TICK_DURATION` steps as fit in `frame_time`, carrying the remainder to the next frame.
+    /// Gameplay therefore doesn't depend on the frame rate; the leftover fraction is used to interpolate drawing.
+    pub fn advance(&mut self, frame_time: f32) {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        while self.accumulator >= TICK_DURATION && !self.game.gameover && !self.input_source.is_finished() {
+            self.step(TICK_DURATION);
+            self.accumulator -= TICK_DURATION;
+        }
+        self.game.interpolation = (self.accumulator / TICK_DURATION).clamp(0.0, 1.0);
+    }
+
+    /// Steps until `tick_count` ticks have passed or the game is over.
+    pub fn run(&mut self, tick_count: u64, delta_t: f32) {
+        for _ in 0..tick_count {
+            if self.game.gameover || self.input_source.is_finished() {
+                break;
+            }
+            self.step(delta_t);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use macroquad::prelude as mq;
+
+    use super::*;
+    use crate::input::IdleInputSource;
+    use crate::level::level_file::LevelFile;
+
+    /// Level 1 with the built-in archetypes and no upgrades, driven by `input_source`
+    pub fn new_level_1_simulation(seed: u64, input_source: Box<dyn InputSource>) -> Simulation {
+        let archetypes = EnemyArchetypes::default();
+        let level = LevelFile::built_in(&archetypes).expect("the built-in level is valid");
+        let game = Game::new(&level, &archetypes, &Upgrades::default(), 0, seed).expect("Level 1 has bases");
+        Simulation::new(game, input_source)
+    }
+
+    /// The level in `level_file`, written the same way as the files in `levels/`
+    pub fn new_simulation(level_file: &str, input_source: Box<dyn InputSource>) -> Simulation {
+        let archetypes = EnemyArchetypes::default();
+        let level = LevelFile::parse(level_file, &archetypes).expect("valid level");
+        let game = Game::new(&level, &archetypes, &Upgrades::default(), 0, 1).expect("any level makes a game");
+        Simulation::new(game, input_source)
+    }
+
+    /// A level with nothing in it, for tests to add just the ships and bullets they need
+    pub fn new_empty_simulation(input_source: Box<dyn InputSource>) -> Simulation {
+        new_simulation(r#"(name: "Empty", bases: [])"#, input_source)
+    }
+
+    /// Adds a hostile enemy of the archetype called `name` at `position`, guarding nothing
+    pub fn add_enemy(game: &mut Game, name: &str, position: mq::Vec2) -> EntityId {
+        let archetypes = EnemyArchetypes::default();
+        let archetype = archetypes.get(&EnemyShipType::from_name(name)).expect("a built-in archetype");
+        let mut enemy = Ship::new_enemy(archetype, None, 0.0, Team::Hostile);
+        enemy.id = game.entity_ids.allocate();
+        teleport(&mut enemy.game_object, position);
+        let id = enemy.id;
+        game.enemies.push(enemy); // Newest id last, so they stay sorted
+        id
+    }
+
+    /// Adds one of the player's ordinary shots at `position`, flying at `velocity`
+    pub fn add_player_bullet(game: &mut Game, position: mq::Vec2, velocity: mq::Vec2) -> EntityId {
+        let mut bullet = Bullet::new_bullet(&game.player, &Default::default(), 0.0, 5.0, velocity.length(), 1.0);
+        bullet.id = game.entity_ids.allocate();
+        teleport(&mut bullet.game_object, position);
+        bullet.game_object.velocity = velocity;
+        bullet.game_object.direction = Direction::new_from_vec(velocity);
+        let id = bullet.id;
+        game.bullets.push(bullet);
+        id
+    }
+
+    /// Moves an object somewhere without it sweeping through everything in between
+    pub fn teleport(game_object: &mut GameObject, position: mq::Vec2) {
+        game_object.position = position;
+        game_object.previous_position = position;
+    }
+
+    #[test]
+    fn player_coasts_without_input() {
+        let mut simulation = new_level_1_simulation(1, Box::new(IdleInputSource));
+        simulation.game.player.game_object.velocity = mq::vec2(100.0, 0.0);
+        simulation.run(60, TICK_DURATION);
+        let player = &simulation.game.player.game_object;
+        assert_eq!(simulation.tick, 60);
+        assert!(player.position.x > 0.0 && player.position.y == 0.0, "moved to {}", player.position);
+        assert!(player.velocity.x > 0.0 && player.velocity.x < 100.0, "friction left {}", player.velocity);
+    }
+
+    #[test]
+    fn touching_a_base_collects_it_and_carries_it() {
+        let mut simulation = new_level_1_simulation(1, Box::new(|_: &Game| PlayerInput { left_mouse: true, ..Default::default() }));
+        let base_position = simulation.game.bases[0].game_object.position;
+        teleport(&mut simulation.game.player.game_object, base_position);
+        // So the guards can't ram it to death and send the base back
+        simulation.game.player.game_object.health_status = HealthStatus::Invulnerable;
+        simulation.game.player_invulnerable_time = f32::INFINITY;
+        simulation.run(1, TICK_DURATION);
+        assert!(simulation.game.bases[0].collected);
+        assert_eq!(simulation.game.collected_base_count, 1);
+        simulation.run(30, TICK_DURATION);
+        let game = &simulation.game;
+        assert_ne!(game.player.game_object.position, base_position, "the closure should have thrust the player away");
+        assert_eq!(game.bases[0].game_object.position, game.player.game_object.position);
+    }
+
+    #[test]
+    fn dying_costs_a_life_and_respawns_at_home() {
+        let mut simulation = new_level_1_simulation(1, Box::new(IdleInputSource));
+        let enemy_position = simulation.game.enemies[0].game_object.position;
+        let player = &mut simulation.game.player.game_object;
+        teleport(player, enemy_position - mq::vec2(60.0, 0.0));
+        player.velocity = mq::vec2(2000.0, 0.0);
+        simulation.run(1, TICK_DURATION);
+        let game = &simulation.game;
+        assert_eq!((game.lives, game.deaths), (2, 1));
+        assert_eq!(game.player.game_object.position, game.home_base.position);
+        assert_eq!(game.player.game_object.health_status, HealthStatus::Invulnerable);
+    }
+
+    #[test]
+    fn losing_the_last_life_ends_the_run() {
+        let mut simulation = new_level_1_simulation(1, Box::new(IdleInputSource));
+        simulation.game.lives = 1;
+        simulation.game.player.game_object.health.hit_points = 0.0;
+        simulation.game.player.game_object.health_status = HealthStatus::Dead;
+        simulation.run(100, TICK_DURATION);
+        assert!(simulation.game.gameover);
+        assert_eq!(simulation.tick, 1, "running stops once the game is over");
+    }
+}
@@ -0,0 +1,83 @@
+use macroquad::prelude as mq;
+
+use crate::prelude::*;
+
+/// A uniform grid over object indices, so overlap checks only look at nearby objects instead of every one.
+/// Each object is listed in every cell its bounding box touches, so any two overlapping objects share a cell.
+/// Rebuilt every tick, so it's stored as one sorted list of `(cell x, cell y, index)` rather than a map of cells.
+#[derive(Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    entries: Vec<(i32, i32, usize)>,
+}
+
+impl SpatialGrid {
+    /// Takes `(index, position, radius)` for each circle; queries return the indices
+    pub fn from_circles(cell_size: f32, circles: impl Iterator<Item = (usize, mq::Vec2, f32)>) -> Self {
+        let mut grid = SpatialGrid { cell_size, entries: vec![] };
+        for (index, position, radius) in circles {
+            let (min, max) = grid.get_cell_range(position, radius);
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    grid.entries.push((x, y, index));
+                }
+            }
+        }
+        grid.entries.sort_unstable();
+        grid
+    }
+
+    /// Indexes `objects` by their position in the iterator, covering the whole path each moved along last tick
+    pub fn from_game_objects<'a>(cell_size: f32, objects: impl Iterator<Item = &'a GameObject>) -> Self {
+        let circles = objects.enumerate().map(|(index, game_object)| {
+            let (position, radius) = game_object.get_swept_bounds();
+            (index, position, radius)
+        });
+        SpatialGrid::from_circles(cell_size, circles)
+    }
+
+    /// Indices of every object whose bounding box might overlap the circle, in ascending order without repeats
+    pub fn query(&self, position: mq::Vec2, radius: f32) -> Vec<usize> {
+        let (min, max) = self.get_cell_range(position, radius);
+        let mut indices = vec![];
+        for x in min.0..=max.0 {
+            // Cells with the same x are next to each other in the sorted list, ordered by y
+            let start = self.entries.partition_point(|&(entry_x, entry_y, _)| (entry_x, entry_y) < (x, min.1));
+            let end = self.entries.partition_point(|&(entry_x, entry_y, _)| (entry_x, entry_y) <= (x, max.1));
+            indices.extend(self.entries[start..end].iter().map(|&(_, _, index)| index));
+        }
+        if min != max {
+            indices.sort_unstable();
+            indices.dedup();
+        }
+        indices
+    }
+
+    fn get_cell_range(&self, position: mq::Vec2, radius: f32) -> ((i32, i32), (i32, i32)) {
+        let min = ((position - radius) / self.cell_size).floor();
+        let max = ((position + radius) / self.cell_size).floor();
+        ((min.x as i32, min.y as i32), (max.x as i32, max.y as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_finds_nearby_circles_once_each() {
+        let circles = [(0, mq::vec2(50.0, 50.0), 10.0), (1, mq::vec2(500.0, 500.0), 10.0), (2, mq::vec2(120.0, 0.0), 150.0)];
+        let grid = SpatialGrid::from_circles(100.0, circles.into_iter());
+        // Circle 2 covers several cells, some shared with circle 0, but is still only listed once
+        assert_eq!(grid.query(mq::vec2(60.0, 60.0), 50.0), vec![0, 2]);
+        assert_eq!(grid.query(mq::vec2(510.0, 490.0), 5.0), vec![1]);
+        assert!(grid.query(mq::vec2(-1000.0, -1000.0), 50.0).is_empty());
+    }
+
+    #[test]
+    fn query_works_across_negative_cells() {
+        let grid = SpatialGrid::from_circles(100.0, [(0, mq::vec2(-5.0, -5.0), 10.0)].into_iter());
+        assert_eq!(grid.query(mq::vec2(3.0, 3.0), 1.0), vec![0]);
+        assert_eq!(grid.query(mq::vec2(-150.0, -150.0), 1.0), Vec::<usize>::new());
+    }
+}
@@ -4,26 +4,35 @@ use macroquad::prelude as mq;
 
 use crate::prelude::*;
 
+#[derive(Clone)]
 pub struct SpawnRegion {
     enemy_counts: HashMap<EnemyShipType, u32>,
     min_distance: f32,
     max_distance: f32,
+    team: Team,
 }
 
 impl SpawnRegion {
     pub fn new(enemy_counts: HashMap<EnemyShipType, u32>, min_distance: f32, max_distance: f32) -> Self {
-        SpawnRegion { enemy_counts, min_distance, max_distance}
+        SpawnRegion { enemy_counts, min_distance, max_distance, team: Team::Hostile }
     }
-    pub fn get_enemies(&self) -> Vec<Ship> {
+    pub fn with_team(mut self, team: Team) -> Self {
+        self.team = team;
+        self
+    }
+    pub fn get_enemies(&self, rng: &mut SeededRng, archetypes: &EnemyArchetypes) -> Vec<Ship> {
         let mut enemies = vec![];
-        for (enemy_type, count) in self.enemy_counts.clone() {
+        let mut enemy_counts: Vec<_> = self.enemy_counts.clone().into_iter().collect();
+        enemy_counts.sort();
+        for (enemy_type, count) in enemy_counts {
+            let archetype = archetypes.get(&enemy_type).expect("level files are checked against the archetypes when loaded");
             for _ in 0..count {
-                enemies.push(Ship::new_enemy(enemy_type, None, 0.0));
+                enemies.push(Ship::new_enemy(archetype, None, 0.0, self.team));
             }
         }
         for enemy in &mut enemies {
-            let direction = mq::rand::gen_range(-PI, PI);
-            let square_distance = mq::rand::gen_range(self.min_distance * self.min_distance, self.max_distance * self.max_distance);
+            let direction = rng.gen_range(-PI, PI);
+            let square_distance = rng.gen_range(self.min_distance * self.min_distance, self.max_distance * self.max_distance);
             let distance = f32::sqrt(square_distance);
             enemy.game_object.position = distance * mq::Vec2::from_angle(direction);
             enemy.game_object.direction = Direction::new(direction);
@@ -1,5 +1,46 @@
-#[derive(Clone, PartialEq, Eq)]
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum Team {
     Player,
     Hostile,
+    Faction(u8), // More enemy sides, for levels where enemies fight each other too
+}
+
+impl Team {
+    pub fn get_color(self) -> mq::Color {
+        match self {
+            Team::Player => mq::SKYBLUE,
+            Team::Hostile => mq::ORANGE,
+            Team::Faction(faction) => [mq::LIME, mq::PINK, mq::VIOLET, mq::YELLOW][faction as usize % 4],
+        }
+    }
+}
+
+/// Who can hurt whom. Different teams are enemies unless allied. Bullets only hurt their own side
+/// when friendly fire is on, and then only once they've flown long enough to clear whoever fired them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TeamRules {
+    #[serde(default)]
+    pub alliances: Vec<(Team, Team)>,
+    #[serde(default)]
+    pub friendly_fire_after: Option<f32>, // Seconds of flight; `None` turns friendly fire off
+}
+
+impl TeamRules {
+    pub fn is_hostile(&self, x: Team, y: Team) -> bool {
+        x != y && !self.alliances.iter().any(|&alliance| alliance == (x, y) || alliance == (y, x))
+    }
+
+    /// Whether a bullet that's been flying for `age` seconds can hurt its own side
+    pub fn allows_friendly_fire(&self, age: f32) -> bool {
+        self.friendly_fire_after.is_some_and(|grace_period| age >= grace_period)
+    }
+
+    /// Whether a bullet fired by `team` `age` seconds ago can hurt a ship on `target_team`
+    pub fn can_damage(&self, team: Team, age: f32, target_team: Team) -> bool {
+        self.is_hostile(team, target_team) || self.allows_friendly_fire(age)
+    }
 }
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Something the player can buy at the home base with banked bases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum UpgradeKind {
+    Thrust,
+    TurnRate,
+    Reload,
+    BulletSpeed,
+    Range,
+    Hull,
+    WeaponSlot,
+}
+
+impl UpgradeKind {
+    /// In shop order, which is also the order of the number keys that buy them
+    pub const ALL: [UpgradeKind; 7] = [
+        UpgradeKind::Thrust,
+        UpgradeKind::TurnRate,
+        UpgradeKind::Reload,
+        UpgradeKind::BulletSpeed,
+        UpgradeKind::Range,
+        UpgradeKind::Hull,
+        UpgradeKind::WeaponSlot,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            UpgradeKind::Thrust => "Thrust",
+            UpgradeKind::TurnRate => "Turn Rate",
+            UpgradeKind::Reload => "Reload",
+            UpgradeKind::BulletSpeed => "Bullet Speed",
+            UpgradeKind::Range => "Range",
+            UpgradeKind::Hull => "Hull",
+            UpgradeKind::WeaponSlot => "Extra Weapon",
+        }
+    }
+
+    pub fn get_index(self) -> usize {
+        UpgradeKind::ALL.iter().position(|&kind| kind == self).expect("every kind is in ALL")
+    }
+
+    pub fn get_max_level(self) -> u32 {
+        match self {
+            UpgradeKind::WeaponSlot => 4,
+            _ => 10,
+        }
+    }
+
+    /// Bases it costs to go from `level` to `level + 1`
+    pub fn get_cost(self, level: u32) -> u32 {
+        match self {
+            UpgradeKind::WeaponSlot => 5 * (level + 1),
+            _ => level + 1,
+        }
+    }
+}
+
+/// How many levels of each upgrade the player has bought
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Upgrades {
+    pub levels: BTreeMap<UpgradeKind, u32>,
+}
+
+impl Upgrades {
+    /// The old uniform level up, where every ship stat grew together with the player level.
+    /// Used to play back replays recorded before the shop.
+    pub fn from_player_level(level: u32) -> Self {
+        let kinds = [UpgradeKind::Thrust, UpgradeKind::TurnRate, UpgradeKind::Reload, UpgradeKind::BulletSpeed];
+        Upgrades { levels: kinds.into_iter().map(|kind| (kind, level)).collect() }
+    }
+
+    pub fn get_level(&self, kind: UpgradeKind) -> u32 {
+        self.levels.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// `None` once the upgrade is maxed out
+    pub fn get_next_cost(&self, kind: UpgradeKind) -> Option<u32> {
+        let level = self.get_level(kind);
+        (level < kind.get_max_level()).then(|| kind.get_cost(level))
+    }
+
+    /// Bases spent on everything bought so far
+    pub fn get_total_cost(&self) -> u32 {
+        self.levels.iter().map(|(&kind, &level)| (0..level).map(|level| kind.get_cost(level)).sum::<u32>()).sum()
+    }
+
+    /// Buys the next level of `kind` if it isn't maxed out and `bases_to_spend` covers it
+    pub fn buy(&mut self, kind: UpgradeKind, bases_to_spend: &mut u32) -> bool {
+        let Some(cost) = self.get_next_cost(kind) else {
+            return false;
+        };
+        if cost > *bases_to_spend {
+            return false;
+        }
+        *bases_to_spend -= cost;
+        *self.levels.entry(kind).or_insert(0) += 1;
+        true
+    }
+}
@@ -2,35 +2,149 @@ use macroquad::prelude as mq;
 
 use crate::prelude::*;
 
+use crate::explosion::ExplosionStats;
+
+use super::bullet_controller::{BulletController, HomingBulletController, StraightBulletController};
+use super::pattern::Mount;
+
+const MINE_RADIUS: f32 = 6.0;
+const MINE_FRICTION_MULTIPLIER: f32 = 0.1;
+const BEAM_THICKNESS: f32 = 2.0;
+const MISSILE_RADIUS: f32 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulletKind {
+    Shot,
+    Beam,    // Lies along its path for a single tick, and isn't used up by hitting things
+    Missile, // Can be shot down
+}
 
 #[derive(Clone)]
 pub struct Bullet {
+    pub id: EntityId,
+    pub owner: EntityId, // The ship that fired it
     pub game_object: GameObject,
     pub lifetime_remaining: f32,
     pub team: Team,
+    pub age: f32, // Seconds since it was fired
+    pub damage: f32,
+    pub kind: BulletKind,
+    pub bullet_controller: Box<dyn BulletController>,
+    pub rotate_speed: f32,
+    pub explosion: Option<ExplosionStats>, // Goes off when the bullet hits something, is shot down or runs out
 }
 
 impl Bullet {
-    pub fn new_bullet(ship: Ship, bullet_lifetime: f32, bullet_speed: f32) -> Self {
-        let fired_from = ship.game_object;
-        let direction_vec = fired_from.direction.get_as_vec();
-        let position = fired_from.position + fired_from.radius * direction_vec;
-        let velocity = fired_from.velocity + bullet_speed * direction_vec;
+    /// `angle_offset` turns the bullet away from the mount's direction, for spread shots
+    pub fn new_bullet(ship: &Ship, mount: &Mount, angle_offset: f32, bullet_lifetime: f32, bullet_speed: f32, damage: f32) -> Self {
+        let fired_from = &ship.game_object;
+        let (position, mut direction) = mount.get_muzzle(fired_from);
+        direction.add_f32(angle_offset);
+        let velocity = fired_from.velocity + bullet_speed * direction.get_as_vec();
         let mut circle = DrawShape::new_circle();
         circle.color = mq::BLACK;
         let game_object = GameObject {
             position,
+            previous_position: position,
             velocity,
             radius: 2.0,
             draw_shapes: vec![circle],
             direction: Direction::new_from_vec(velocity),
             ..GameObject::default()
         };
-        Bullet { game_object, lifetime_remaining: bullet_lifetime, team: ship.team }
+        Bullet {
+            id: EntityId::default(), owner: ship.id,
+            game_object, lifetime_remaining: bullet_lifetime, team: ship.team, age: 0.0, damage, kind: BulletKind::Shot,
+            bullet_controller: Box::new(StraightBulletController), rotate_speed: 0.0, explosion: None,
+        }
+    }
+
+    pub fn new_mine(ship: &Ship, mount: &Mount, lifetime: f32, speed: f32, damage: f32) -> Self {
+        let mut mine = Bullet::new_bullet(ship, mount, 0.0, lifetime, speed, damage);
+        mine.game_object.radius = MINE_RADIUS;
+        mine.game_object.friction_multiplier = MINE_FRICTION_MULTIPLIER;
+        mine.game_object.draw_shapes = vec![DrawShape::new_polygon_color(4, mq::RED), DrawShape::new_circle_color(mq::MAROON)];
+        mine
+    }
+
+    /// The beam runs from `previous_position` to `position` for the one tick it exists
+    pub fn new_beam(ship: &Ship, mount: &Mount, length: f32, damage: f32) -> Self {
+        let (start, direction) = mount.get_muzzle(&ship.game_object);
+        let game_object = GameObject {
+            position: start + length * direction.get_as_vec(),
+            previous_position: start,
+            radius: BEAM_THICKNESS,
+            direction,
+            ..GameObject::default()
+        };
+        Bullet {
+            id: EntityId::default(), owner: ship.id,
+            game_object, lifetime_remaining: f32::INFINITY, team: ship.team, age: 0.0, damage, kind: BulletKind::Beam,
+            bullet_controller: Box::new(StraightBulletController), rotate_speed: 0.0, explosion: None,
+        }
+    }
+
+    /// Flies at a constant speed, turning at up to `rotate_speed` toward the nearest target in `seek_range` until its lifetime runs out
+    pub fn new_missile(ship: &Ship, mount: &Mount, lifetime: f32, speed: f32, damage: f32, rotate_speed: f32, seek_range: f32) -> Self {
+        let mut missile = Bullet::new_bullet(ship, mount, 0.0, lifetime, speed, damage);
+        missile.kind = BulletKind::Missile;
+        missile.bullet_controller = Box::new(HomingBulletController::new(seek_range));
+        missile.rotate_speed = rotate_speed;
+        missile.game_object.radius = MISSILE_RADIUS;
+        missile.game_object.draw_shapes = vec![DrawShape::new_polygon_color(3, ship.team.get_color())];
+        missile
+    }
+
+    /// Damages `target` and destroys the bullet if they touched this tick. Already destroyed targets are ignored.
+    pub fn hit_if_overlapping(&mut self, target: &mut GameObject) {
+        if self.game_object.health_status == HealthStatus::Dead || target.health_status == HealthStatus::Dead {
+            return;
+        }
+        let touched = if self.kind == BulletKind::Beam {
+            let (start, end) = (self.game_object.previous_position, self.game_object.position);
+            GameObject::is_touching_line_swept(target, start, end, self.game_object.radius)
+        } else {
+            GameObject::is_overlapping_swept(&self.game_object, target)
+        };
+        if touched {
+            target.apply_damage(self.damage);
+            if self.kind != BulletKind::Beam {
+                self.game_object.health_status = HealthStatus::Dead;
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta_t: f32, world: &WorldSnapshot) {
+        if self.kind == BulletKind::Beam {
+            return; // Already where it needs to be
+        }
+        let rotation = self.bullet_controller.get_rotation(self, world).to_f32();
+        if rotation != 0.0 {
+            self.game_object.direction.add_f32(rotation * self.rotate_speed * delta_t);
+            self.game_object.velocity = self.game_object.velocity.length() * self.game_object.direction.get_as_vec();
+        }
+        self.coast(delta_t);
     }
 
-    pub fn update(&mut self, delta_t: f32) {
+    /// Moves and ages the bullet without steering, for bullets too far away to matter
+    pub fn coast(&mut self, delta_t: f32) {
+        if self.kind == BulletKind::Beam {
+            return;
+        }
         self.game_object.update(delta_t);
         self.lifetime_remaining -= delta_t;
+        self.age += delta_t;
+    }
+
+    pub fn draw(&self, camera: &Camera, interpolation: f32) {
+        match self.kind {
+            BulletKind::Shot | BulletKind::Missile => self.game_object.draw(camera, interpolation),
+            BulletKind::Beam => {
+                let start = (self.game_object.previous_position - camera.position) * camera.zoom;
+                let end = (self.game_object.position - camera.position) * camera.zoom;
+                let thickness = f32::max(1.0, BEAM_THICKNESS * camera.zoom);
+                mq::draw_line(start.x, start.y, end.x, end.y, thickness, self.team.get_color());
+            }
+        }
     }
 }
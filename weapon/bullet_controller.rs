@@ -0,0 +1,58 @@
+use macroquad::prelude as mq;
+use dyn_clone::DynClone;
+
+use crate::prelude::*;
+
+/// Steers a bullet after it's fired. Most bullets fly straight; missiles home in on a target.
+pub trait BulletController: DynClone {
+    fn get_rotation(&self, bullet: &Bullet, world: &WorldSnapshot) -> RotationDirection;
+}
+
+dyn_clone::clone_trait_object!(BulletController);
+
+#[derive(Clone)]
+pub struct StraightBulletController;
+
+impl BulletController for StraightBulletController {
+    fn get_rotation(&self, _bullet: &Bullet, _world: &WorldSnapshot) -> RotationDirection {
+        RotationDirection::None
+    }
+}
+
+/// Turns toward the nearest ship it could hurt within `seek_range`, the same way ships turn toward their targets
+#[derive(Clone)]
+pub struct HomingBulletController {
+    pub seek_range: f32,
+    epsilon: f32,
+}
+
+impl HomingBulletController {
+    pub fn new(seek_range: f32) -> Self {
+        HomingBulletController { seek_range, epsilon: PI / 90.0 }
+    }
+
+    fn get_target(&self, bullet: &Bullet, world: &WorldSnapshot) -> Option<mq::Vec2> {
+        let position = bullet.game_object.position;
+        // Enemy missiles only need to look at enemies when some of them are on different sides
+        let nearby_enemies = if bullet.team == Team::Player || world.enemies_fight_each_other {
+            world.enemy_grid.query(position, self.seek_range)
+        } else {
+            vec![]
+        };
+        std::iter::once(&world.player).chain(nearby_enemies.into_iter().map(|index| &world.enemies[index]))
+            .filter(|ship| world.team_rules.is_hostile(bullet.team, ship.team))
+            .map(|ship| ship.position)
+            .filter(|target| target.distance(position) < self.seek_range)
+            .min_by(|x, y| x.distance_squared(position).total_cmp(&y.distance_squared(position)))
+    }
+}
+
+impl BulletController for HomingBulletController {
+    fn get_rotation(&self, bullet: &Bullet, world: &WorldSnapshot) -> RotationDirection {
+        let Some(target) = self.get_target(bullet, world) else {
+            return RotationDirection::None;
+        };
+        let target_direction = Direction::new_from_vec(target - bullet.game_object.position);
+        Direction::get_shorter_rotation_direction(bullet.game_object.direction, target_direction, self.epsilon)
+    }
+}
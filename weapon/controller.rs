@@ -3,10 +3,10 @@ use dyn_clone::DynClone;
 
 use crate::prelude::*;
 
-use crate::keybinds;
+use crate::ship::targeting::{TargetSelector, TargetingMode};
 
 pub trait WeaponController: DynClone {
-    fn is_trying_to_fire (&self, ship: &Ship, game: &Game) -> bool;
+    fn is_trying_to_fire (&self, weapon: &Weapon, ship: &Ship, world: &WorldSnapshot) -> bool;
 }
 
 dyn_clone::clone_trait_object!(WeaponController);
@@ -15,8 +15,8 @@ dyn_clone::clone_trait_object!(WeaponController);
 pub struct KeyboardWeaponController;
 
 impl WeaponController for KeyboardWeaponController {
-    fn is_trying_to_fire (&self, _ship: &Ship, _game: &Game) -> bool {
-        mq::is_key_down(keybinds::FIRE_KEY)
+    fn is_trying_to_fire (&self, _weapon: &Weapon, _ship: &Ship, world: &WorldSnapshot) -> bool {
+        world.input.fire_key
     }
 }
 
@@ -25,27 +25,41 @@ pub struct MouseWeaponController;
 
 
 impl WeaponController for MouseWeaponController {
-    fn is_trying_to_fire (&self, _ship: &Ship, _game: &Game) -> bool {
-        mq::is_mouse_button_down(mq::MouseButton::Right)
+    fn is_trying_to_fire (&self, _weapon: &Weapon, _ship: &Ship, world: &WorldSnapshot) -> bool {
+        world.input.right_mouse
     }
 }
 
+/// Fires when lined up with where its target will be. Picks targets the same way as the ship it's on,
+/// except that it has no base to protect. When enemies fight each other it only picks from ships in the
+/// weapon's range, so it doesn't waste shots on a far-off player while another side is close by.
+/// Otherwise the player is the only target, and is shot at from any distance.
 #[derive(Clone)]
-pub struct EnemyWeaponController;
+pub struct EnemyWeaponController {
+    targeting: TargetingMode,
+}
+
+impl EnemyWeaponController {
+    pub fn new(targeting: TargetingMode) -> Self {
+        EnemyWeaponController { targeting }
+    }
+}
 
 impl WeaponController for EnemyWeaponController {
-    fn is_trying_to_fire (&self, ship: &Ship, game: &Game) -> bool {
-        if let Some(weapon) = ship.weapons.first() {
-            let distance = mq::Vec2::distance(ship.game_object.position, game.player.game_object.position);
-            let speed = weapon.bullet_speed;
-            let time = distance / speed;
-            let future_pos = game.player.game_object.position + time * game.player.game_object.velocity;
-            let future_offset = future_pos - ship.game_object.position;
-            let future_direction = Direction::new_from_vec(future_offset);
-            let rotation_direction = Direction::get_shorter_rotation_direction(ship.game_object.direction, future_direction, PI / 18.0);
-            return rotation_direction == RotationDirection::None;
-        }
-        false // Can't fire if there's no weapon
+    fn is_trying_to_fire (&self, weapon: &Weapon, ship: &Ship, world: &WorldSnapshot) -> bool {
+        let range = if world.enemies_fight_each_other { weapon.get_range() } else { f32::INFINITY };
+        let selector = TargetSelector::new(self.targeting, range);
+        let Some(target) = selector.select(ship, None, world) else {
+            return false; // Nothing in range to shoot at
+        };
+        let distance = mq::Vec2::distance(ship.game_object.position, target.position);
+        let speed = weapon.bullet_speed;
+        let time = distance / speed;
+        let future_pos = target.position + time * target.velocity;
+        let future_offset = future_pos - ship.game_object.position;
+        let future_direction = Direction::new_from_vec(future_offset);
+        let rotation_direction = Direction::get_shorter_rotation_direction(ship.game_object.direction, future_direction, PI / 18.0);
+        rotation_direction == RotationDirection::None
     }
 }
 
@@ -53,14 +67,14 @@ impl WeaponController for EnemyWeaponController {
 pub struct EnemyCloneWeaponController;
 
 impl WeaponController for EnemyCloneWeaponController {
-    fn is_trying_to_fire (&self, _ship: &Ship, _game: &Game) -> bool {
+    fn is_trying_to_fire (&self, _weapon: &Weapon, _ship: &Ship, _world: &WorldSnapshot) -> bool {
         true /*
         if let Some(weapon) = ship.weapons.first() {
-            let distance = Vec2::distance(ship.game_object.position, game.player.game_object.position);
-            let speed_towards_player = ship.game_object.velocity.dot((game.player.game_object.position - ship.game_object.position).normalize());
+            let distance = Vec2::distance(ship.game_object.position, world.player.position);
+            let speed_towards_player = ship.game_object.velocity.dot((world.player.position - ship.game_object.position).normalize());
             let speed = weapon.bullet_speed + speed_towards_player;
             let time = distance / speed;
-            let future_pos = game.player.game_object.position + time * game.player.game_object.velocity;
+            let future_pos = world.player.position + time * world.player.velocity;
             let future_offset = future_pos - ship.game_object.position;
             let future_direction = Direction::new_from_vec(future_offset);
 
@@ -1,9 +1,16 @@
 use crate::prelude::*;
+use crate::ship::archetype::EnemyWeaponStats;
+use crate::explosion::ExplosionStats;
+use crate::ship::targeting::TargetingMode;
 #[allow(unused_imports)]
 use controller::{WeaponController, MouseWeaponController, KeyboardWeaponController, EnemyWeaponController, EnemyCloneWeaponController};
 
 pub mod controller;
 pub mod bullet;
+pub mod bullet_controller;
+pub mod pattern;
+
+use pattern::{FiringPattern, Mount};
 
 #[derive(Clone)]
 pub struct Weapon {
@@ -12,50 +19,176 @@ pub struct Weapon {
     pub time_until_reloaded: f32,
     pub bullet_lifetime: f32,
     pub bullet_speed: f32,
+    pub damage: f32,
+    pub pattern: FiringPattern,
+    pub mount: Mount,
+    pub explosion: Option<ExplosionStats>, // Makes every bullet fired explosive, except beams
+    burst_shots_remaining: u32,
+    time_until_burst_shot: f32,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon {
+            weapon_controller: Box::new(EnemyCloneWeaponController),
+            reload_time: 1.0,
+            time_until_reloaded: 0.0,
+            bullet_lifetime: 5.0,
+            bullet_speed: 200.0,
+            damage: 1.0,
+            pattern: FiringPattern::Single,
+            mount: Mount::default(),
+            explosion: None,
+            burst_shots_remaining: 0,
+            time_until_burst_shot: 0.0,
+        }
+    }
 }
 
 impl Weapon {
-    pub fn new_player_weapon(level: f32) -> Self {
-        let bullet_speed = 300.0 + 50.0 * level;
-        let range = 600.0;
+    pub fn new_player_weapon(upgrades: &Upgrades) -> Self {
+        let bullet_speed = 300.0 + 50.0 * upgrades.get_level(UpgradeKind::BulletSpeed) as f32;
+        let range = 600.0 + 100.0 * upgrades.get_level(UpgradeKind::Range) as f32;
         Weapon {
             weapon_controller: Box::new(MouseWeaponController),
-            reload_time: 4.0 / (level + 5.0),
+            reload_time: 4.0 / (upgrades.get_level(UpgradeKind::Reload) as f32 + 5.0),
             time_until_reloaded: 0.0,
             bullet_lifetime: range / bullet_speed,
             bullet_speed,
+            ..Default::default()
         }
     }
 
-    fn new_enemy_weapon() -> Self {
+    /// The weapon in the player's extra weapon slot `slot`, counting from 1. Each slot adds a different kind of weapon.
+    pub fn new_player_extra_weapon(slot: u32, upgrades: &Upgrades) -> Self {
+        let main_weapon = Weapon::new_player_weapon(upgrades);
+        let range = main_weapon.get_range();
+        match slot {
+            1 => Weapon {
+                reload_time: main_weapon.reload_time * 2.0,
+                pattern: FiringPattern::Spread { count: 3, angle: 0.4 },
+                ..main_weapon
+            },
+            2 => Weapon {
+                reload_time: 1.0,
+                bullet_lifetime: 20.0,
+                bullet_speed: 50.0,
+                damage: 3.0,
+                pattern: FiringPattern::Mine,
+                mount: Mount::new_rear(),
+                explosion: Some(ExplosionStats { radius: 80.0, damage: 3.0, knockback: 30000.0 }),
+                ..main_weapon
+            },
+            3 => Weapon {
+                reload_time: 1.5,
+                bullet_lifetime: 4.0,
+                bullet_speed: 250.0,
+                damage: 2.0,
+                pattern: FiringPattern::Homing { turn_rate: 2.5, seek_range: 800.0 },
+                explosion: Some(ExplosionStats { radius: 40.0, damage: 2.0, knockback: 10000.0 }),
+                ..main_weapon
+            },
+            _ => Weapon {
+                reload_time: 0.0, // Fires every tick
+                damage: 3.0,
+                pattern: FiringPattern::Beam { length: range / 2.0 },
+                ..main_weapon
+            },
+        }
+    }
+
+    pub fn new_enemy_weapon(targeting: TargetingMode) -> Self {
         Weapon {
-            weapon_controller: Box::new(EnemyWeaponController),
+            weapon_controller: Box::new(EnemyWeaponController::new(targeting)),
             reload_time: 2.5,
-            time_until_reloaded: 0.0,
-            bullet_lifetime: 5.0,
-            bullet_speed: 200.0,
+            ..Default::default()
         }
     }
 
     pub fn new_enemy_clone_weapon() -> Self {
-        Weapon {
-            weapon_controller: Box::new(EnemyCloneWeaponController),
-            reload_time: 1.0,
-            time_until_reloaded: 0.0,
-            bullet_lifetime: 5.0,
-            bullet_speed: 200.0,
+        Weapon::default()
+    }
+
+    pub fn new_enemy_weapon_from_stats(stats: &EnemyWeaponStats, targeting: TargetingMode) -> Self {
+        let mut weapon = if stats.aimed {
+            Weapon::new_enemy_weapon(targeting)
+        } else {
+            Weapon::new_enemy_clone_weapon()
+        };
+        weapon.reload_time = stats.reload_time;
+        weapon.bullet_lifetime = stats.bullet_lifetime;
+        weapon.bullet_speed = stats.bullet_speed;
+        weapon.damage = stats.damage;
+        weapon.pattern = stats.pattern;
+        weapon.mount = stats.mount;
+        weapon.explosion = stats.explosion;
+        weapon
+    }
+
+    /// How far its shots reach
+    pub fn get_range(&self) -> f32 {
+        match self.pattern {
+            FiringPattern::Beam { length } => length,
+            _ => self.bullet_speed * self.bullet_lifetime,
         }
     }
 
-    pub fn update(&mut self, delta_t: f32, ship: &Ship, game: &Game, bullets_to_add: &mut Vec<Bullet>) {
+    pub fn update(&mut self, delta_t: f32, ship: &Ship, world: &WorldSnapshot, bullets_to_add: &mut Vec<Bullet>) {
         self.time_until_reloaded -= delta_t;
-        if self.weapon_controller.is_trying_to_fire(ship, game) {
-            while self.time_until_reloaded <= 0.0 {
+        // A burst finishes even if the trigger is let go
+        if self.burst_shots_remaining > 0 {
+            self.time_until_burst_shot -= delta_t;
+            if self.time_until_burst_shot <= 0.0 {
+                self.burst_shots_remaining -= 1;
+                if let FiringPattern::Burst { interval, .. } = self.pattern {
+                    self.time_until_burst_shot += interval;
+                }
+                bullets_to_add.push(self.new_bullet(ship, 0.0));
+            }
+        }
+        if self.weapon_controller.is_trying_to_fire(self, ship, world) {
+            if self.time_until_reloaded <= 0.0 {
                 self.time_until_reloaded += self.reload_time;
-                bullets_to_add.push(Bullet::new_bullet(ship.clone(), self.bullet_lifetime, self.bullet_speed));
+                self.fire(delta_t, ship, bullets_to_add);
             }
         } else {
             self.time_until_reloaded = self.time_until_reloaded.max(0.0);
         }
     }
+
+    fn fire(&mut self, delta_t: f32, ship: &Ship, bullets_to_add: &mut Vec<Bullet>) {
+        match self.pattern {
+            FiringPattern::Single => bullets_to_add.push(self.new_bullet(ship, 0.0)),
+            FiringPattern::Spread { count, angle } => {
+                for index in 0..count {
+                    let fraction = if count > 1 { index as f32 / (count - 1) as f32 - 0.5 } else { 0.0 };
+                    bullets_to_add.push(self.new_bullet(ship, fraction * angle));
+                }
+            }
+            FiringPattern::Burst { count, interval } => {
+                bullets_to_add.push(self.new_bullet(ship, 0.0));
+                self.burst_shots_remaining = count.saturating_sub(1);
+                self.time_until_burst_shot = interval;
+            }
+            FiringPattern::Mine => {
+                let mut mine = Bullet::new_mine(ship, &self.mount, self.bullet_lifetime, self.bullet_speed, self.damage);
+                mine.explosion = self.explosion;
+                bullets_to_add.push(mine);
+            }
+            FiringPattern::Homing { turn_rate, seek_range } => {
+                let mut missile = Bullet::new_missile(ship, &self.mount, self.bullet_lifetime, self.bullet_speed, self.damage, turn_rate, seek_range);
+                missile.explosion = self.explosion;
+                bullets_to_add.push(missile);
+            }
+            FiringPattern::Beam { length } => {
+                bullets_to_add.push(Bullet::new_beam(ship, &self.mount, length, self.damage * delta_t));
+            }
+        }
+    }
+
+    fn new_bullet(&self, ship: &Ship, angle_offset: f32) -> Bullet {
+        let mut bullet = Bullet::new_bullet(ship, &self.mount, angle_offset, self.bullet_lifetime, self.bullet_speed, self.damage);
+        bullet.explosion = self.explosion;
+        bullet
+    }
 }
@@ -0,0 +1,41 @@
+use macroquad::prelude as mq;
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+/// What a weapon does each time it's ready and the trigger is held
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum FiringPattern {
+    #[default]
+    Single,
+    Spread { count: u32, angle: f32 },          // `count` bullets fanned evenly across `angle` radians
+    Burst { count: u32, interval: f32 },        // `count` bullets `interval` seconds apart per reload
+    Mine,                                       // A slow shot that drags to a stop and waits
+    Homing { turn_rate: f32, seek_range: f32 }, // A missile that steers toward the nearest target and can be shot down
+    Beam { length: f32 },                       // Hits everything along its length every tick; damage is per second
+}
+
+/// Where a weapon sits on its ship and which way it points, relative to the ship's facing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mount {
+    #[serde(default)]
+    pub angle: f32,
+    #[serde(default)]
+    pub offset: (f32, f32), // Forward, then to the right
+}
+
+impl Mount {
+    pub fn new_rear() -> Self {
+        Mount { angle: PI, offset: (0.0, 0.0) }
+    }
+
+    /// The point on the ship's hull the weapon fires from, and the direction it fires in
+    pub fn get_muzzle(&self, ship: &GameObject) -> (mq::Vec2, Direction) {
+        let mut direction = ship.direction;
+        direction.add_f32(self.angle);
+        let forward = ship.direction.get_as_vec();
+        let offset = forward * self.offset.0 + forward.perp() * self.offset.1;
+        (ship.position + offset + ship.radius * direction.get_as_vec(), direction)
+    }
+}
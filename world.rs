@@ -0,0 +1,108 @@
+use macroquad::prelude as mq;
+
+use crate::prelude::*;
+
+use crate::spatial_grid::SpatialGrid;
+
+/// What controllers can see of the game, taken at the start of each tick so ships and bullets can be
+/// updated in place while their controllers look at everything else as it was.
+/// Only plain copies of what steering and aiming need, so it's far cheaper than cloning the whole game.
+#[derive(Clone)]
+pub struct WorldSnapshot {
+    pub input: PlayerInput,
+    pub elapsed_time: f32,
+    pub player: ShipSnapshot,
+    pub enemies: Vec<ShipSnapshot>, // In the same order as `game.enemies`, which is also id order
+    pub bullets: Vec<BulletSnapshot>,
+    pub bases: Vec<BaseSnapshot>,
+    pub enemy_grid: SpatialGrid,  // Nearby enemies, for controllers that look for neighbours
+    pub target_grid: SpatialGrid, // Every enemy, only built when enemies fight each other
+    pub enemies_fight_each_other: bool,
+    pub team_rules: TeamRules,
+}
+
+impl WorldSnapshot {
+    /// The player or enemy with this id, if it was still around at the start of the tick
+    pub fn get_ship(&self, id: EntityId) -> Option<&ShipSnapshot> {
+        if id == self.player.id {
+            return Some(&self.player);
+        }
+        self.enemies.binary_search_by_key(&id, |enemy| enemy.id).ok().map(|index| &self.enemies[index])
+    }
+
+    pub fn get_base(&self, id: EntityId) -> Option<&BaseSnapshot> {
+        self.bases.iter().find(|base| base.id == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShipSnapshot {
+    pub id: EntityId,
+    pub position: mq::Vec2,
+    pub velocity: mq::Vec2,
+    pub direction: Direction,
+    pub team: Team,
+    pub health: f32, // Hit points plus shield
+}
+
+impl From<&Ship> for ShipSnapshot {
+    fn from(ship: &Ship) -> Self {
+        let game_object = &ship.game_object;
+        ShipSnapshot {
+            id: ship.id,
+            position: game_object.position,
+            velocity: game_object.velocity,
+            direction: game_object.direction,
+            team: ship.team,
+            health: game_object.health.hit_points + game_object.health.shield,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BulletSnapshot {
+    pub id: EntityId,
+    pub owner: EntityId,
+    pub position: mq::Vec2,
+    pub velocity: mq::Vec2,
+    pub direction: Direction,
+    pub team: Team,
+}
+
+impl From<&Bullet> for BulletSnapshot {
+    fn from(bullet: &Bullet) -> Self {
+        BulletSnapshot {
+            id: bullet.id,
+            owner: bullet.owner,
+            position: bullet.game_object.position,
+            velocity: bullet.game_object.velocity,
+            direction: bullet.game_object.direction,
+            team: bullet.team,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BaseSnapshot {
+    pub id: EntityId,
+    pub position: mq::Vec2, // Follows the player while collected
+    pub origin: mq::Vec2,
+    pub optimal_distance: f32,
+    pub max_distance: f32,
+    pub collected: bool,
+    pub team: Team,
+}
+
+impl From<&Base> for BaseSnapshot {
+    fn from(base: &Base) -> Self {
+        BaseSnapshot {
+            id: base.id,
+            position: base.game_object.position,
+            origin: base.origin,
+            optimal_distance: base.optimal_distance,
+            max_distance: base.max_distance,
+            collected: base.collected,
+            team: base.team,
+        }
+    }
+}